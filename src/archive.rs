@@ -0,0 +1,112 @@
+use crate::board;
+use crate::date::Date;
+use crate::item::{next_id, Item};
+use crate::status::{
+    forget_prerequisites, new_lists, subtree_end, Format, Line, ParseError, Status,
+};
+use crate::storage::Storage;
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+
+// Completed items are moved out of a TODO file into its archive next to it, `TODO.archive`
+// for `TODO`. The archive is always written in the `TODO:`/`DONE:` format, which keeps
+// the completion time of every item whatever the format of the TODO file.
+pub fn archive_path(file_path: &str) -> String {
+    format!("{}.archive", file_path)
+}
+
+// Start and end of the subtrees of a closed list to archive: the top level items completed
+// before the cutoff, or all of them without one, along with their subtasks.
+fn archived_ranges(list: &[Item], before: Option<Date>) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut index = 0;
+    while index < list.len() {
+        let end = subtree_end(list, index);
+        let completed = list[index].completed.map(Date::from_timestamp);
+        let archived = match before {
+            Some(before) => completed.is_some_and(|completed| completed < before),
+            None => true,
+        };
+        if archived {
+            ranges.push((index, end));
+        }
+        index = end;
+    }
+    ranges
+}
+
+// load : Reads the archive of a TODO file. A new archive starts with the column definitions
+// of the TODO file, so that it can be opened on its own.
+pub fn load(file_path: &str, lines: &[Line]) -> Result<(Vec<Vec<Item>>, Vec<Line>), String> {
+    let path = archive_path(file_path);
+    let mut archived = new_lists();
+    let mut archive_lines = Vec::new();
+    let mut errors = Vec::<ParseError>::new();
+    match fs::read_to_string(&path) {
+        Ok(text) => {
+            if board::header(&text)
+                .is_some_and(|columns| columns.ok().as_deref() != Some(board::columns()))
+            {
+                return Err(format!("`{}` defines different columns", path));
+            }
+            Format::Todo.load(&text, &mut archived, &mut archive_lines, &mut errors)?;
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            for line in lines.iter() {
+                if let Line::Raw(text) = line {
+                    if board::is_header(text) {
+                        archive_lines.push(Line::Raw(text.clone()));
+                    }
+                }
+            }
+        }
+        Err(error) => return Err(format!("could not read `{}`: {}", path, error)),
+    }
+    Ok((archived, archive_lines))
+}
+
+// archive : Moves the closed items completed before `before`, or all of them, to the end of
+// the archive of the file, with new ids there and without the items they waited for. The items
+// only leave the lists once the archive is saved, the ones left stop waiting for them.
+// Returns how many items were archived.
+pub fn archive(
+    file_path: &str,
+    lists: &mut [Vec<Item>],
+    lines: &[Line],
+    before: Option<Date>,
+) -> Result<usize, String> {
+    let (mut archived, archive_lines) = load(file_path, lines)?;
+    let mut id = next_id(archived.iter().flatten());
+    let mut archived_ids = HashSet::new();
+    let ranges: Vec<(Status, Vec<(usize, usize)>)> = Status::all()
+        .filter(Status::is_closed)
+        .map(|status| (status, archived_ranges(&lists[status.index()], before)))
+        .collect();
+    for (status, ranges) in ranges.iter() {
+        let list = &lists[status.index()];
+        for &(start, end) in ranges.iter() {
+            for item in list[start..end].iter() {
+                archived_ids.insert(item.id);
+                let mut item = Item { id, ..item.clone() };
+                item.set_prerequisites(&[]);
+                archived[status.index()].push(item);
+                id += 1;
+            }
+        }
+    }
+    if archived_ids.is_empty() {
+        return Ok(0);
+    }
+    let path = archive_path(file_path);
+    if let Err(error) = Format::Todo.save(&path, &archived, &archive_lines) {
+        return Err(format!("could not save `{}`: {}", path, error));
+    }
+    for (status, ranges) in ranges.iter() {
+        for &(start, end) in ranges.iter().rev() {
+            lists[status.index()].drain(start..end);
+        }
+    }
+    forget_prerequisites(lists, &archived_ids);
+    Ok(archived_ids.len())
+}
@@ -0,0 +1,241 @@
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+// Columns are defined one per line, in the order of their panels:
+//      column <name> [prefix:<PREFIX>] [mark:<c>] [color:<color>] [next:<PREFIX>] [closed]
+// in the config file, or anywhere in the TODO file behind HEADER_PREFIX, e.g.
+//      # todo-rs column Review prefix:REVIEW color:blue
+// The columns of the TODO file win over the ones of the config file. Files opened together
+// share one board.
+const COLUMN_KEYWORD: &str = "column ";
+pub const HEADER_PREFIX: &str = "# todo-rs ";
+
+// Background colors a column can have, items are written in white (black on the light ones).
+const COLORS: [(&str, i16); 8] = [
+    ("black", ncurses::COLOR_BLACK),
+    ("red", ncurses::COLOR_RED),
+    ("green", ncurses::COLOR_GREEN),
+    ("yellow", ncurses::COLOR_YELLOW),
+    ("blue", ncurses::COLOR_BLUE),
+    ("magenta", ncurses::COLOR_MAGENTA),
+    ("cyan", ncurses::COLOR_CYAN),
+    ("white", ncurses::COLOR_WHITE),
+];
+
+// struct Column is one state of the board, with its own list and panel.
+//      name : The panel header.
+//      prefix : Marks its items in the file, `TODO:`, and is the keyword of other formats.
+//      mark : The character in the checkbox of its items, `[ ]` or `[x]`.
+//      color : Background of its panel, an ncurses color.
+//      closed : Its items are finished, they get a completion time and can be deleted.
+//      next : Prefix of the column Enter moves its items to, see `Status::advance`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub prefix: String,
+    pub mark: char,
+    pub color: i16,
+    pub closed: bool,
+    pub next: Option<String>,
+}
+
+impl Column {
+    fn new(name: &str, mark: char, closed: bool, next: Option<&str>) -> Self {
+        Column {
+            name: name.to_string(),
+            prefix: name.to_string(),
+            mark,
+            color: ncurses::COLOR_GREEN,
+            closed,
+            next: next.map(|next| next.to_string()),
+        }
+    }
+}
+
+// The board used when neither the TODO file nor the config file define columns.
+pub fn default_columns() -> Vec<Column> {
+    vec![
+        Column::new("TODO", ' ', false, None),
+        Column::new("IN-PROGRESS", '/', false, Some("DONE")),
+        Column::new("BLOCKED", '!', false, Some("IN-PROGRESS")),
+        Column::new("DONE", 'x', true, Some("TODO")),
+        Column::new("CANCELLED", '-', true, Some("TODO")),
+    ]
+}
+
+static COLUMNS: OnceLock<Vec<Column>> = OnceLock::new();
+
+// columns : The board in use, the default one until `init` is called.
+pub fn columns() -> &'static [Column] {
+    COLUMNS.get_or_init(default_columns)
+}
+
+// init : Picks the board for the rest of the run, before any list is loaded.
+pub fn init(columns: Vec<Column>) {
+    if COLUMNS.set(columns).is_err() {
+        unreachable!("the board is picked only once");
+    }
+}
+
+// Reads the columns of a board out of its definition lines, see COLUMN_KEYWORD.
+// Marks that are not given default to ` ` for the first column, `x` for the first closed one
+// and the first letter of the name for the others. Without a closed column the last one is.
+pub fn parse_columns<'a>(lines: impl Iterator<Item = &'a str>) -> Result<Vec<Column>, String> {
+    let mut columns: Vec<Column> = Vec::new();
+    let mut marks = Vec::new();
+    for line in lines {
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            return Err("a column needs a name".to_string());
+        };
+        let mut column = Column {
+            prefix: name.to_uppercase(),
+            ..Column::new(name, ' ', false, None)
+        };
+        let mut mark = None;
+        for word in words {
+            match word.split_once(':') {
+                None if word == "closed" => column.closed = true,
+                Some(("prefix", prefix)) if !prefix.is_empty() && !prefix.contains(':') => {
+                    column.prefix = prefix.to_string();
+                }
+                Some(("mark", value)) if value.chars().count() == 1 => mark = value.chars().next(),
+                Some(("color", color)) => match COLORS
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(color))
+                {
+                    Some((_, color)) => column.color = *color,
+                    None => return Err(format!("unknown color `{}` of column `{}`", color, name)),
+                },
+                Some(("next", next)) => column.next = Some(next.to_string()),
+                _ => return Err(format!("unknown setting `{}` of column `{}`", word, name)),
+            }
+        }
+        if columns.iter().any(|other| other.prefix == column.prefix) {
+            return Err(format!("two columns have the prefix `{}`", column.prefix));
+        }
+        columns.push(column);
+        marks.push(mark);
+    }
+
+    match columns.first() {
+        None => return Err("the board has no columns".to_string()),
+        Some(first) if first.closed => {
+            return Err(format!("the first column `{}` can't be closed", first.name))
+        }
+        Some(_) if columns.len() < 2 => {
+            return Err("the board needs at least two columns".to_string())
+        }
+        Some(_) => {}
+    }
+    if !columns.iter().any(|column| column.closed) {
+        if let Some(last) = columns.last_mut() {
+            last.closed = true;
+        }
+    }
+    let first_closed = columns.iter().position(|column| column.closed);
+    for (index, column) in columns.iter_mut().enumerate() {
+        column.mark = match marks[index] {
+            Some(mark) => mark,
+            None if index == 0 => ' ',
+            None if Some(index) == first_closed => 'x',
+            None => column
+                .name
+                .chars()
+                .next()
+                .map_or(' ', |c| c.to_ascii_lowercase()),
+        };
+    }
+    for (index, column) in columns.iter().enumerate() {
+        if columns[..index]
+            .iter()
+            .any(|other| other.mark == column.mark)
+        {
+            return Err(format!(
+                "two columns have the mark `{}`, set one with mark:",
+                column.mark
+            ));
+        }
+        if let Some(next) = &column.next {
+            if !columns.iter().any(|other| other.prefix == *next) {
+                return Err(format!(
+                    "column `{}` moves items to `{}`, which is not a column",
+                    column.name, next
+                ));
+            }
+            if *next == column.prefix {
+                return Err(format!(
+                    "column `{}` can't move items to itself",
+                    column.name
+                ));
+            }
+        }
+    }
+    Ok(columns)
+}
+
+// Where the board of every TODO file is defined, `$XDG_CONFIG_HOME/todo-rs/board`
+// or `~/.config/todo-rs/board`.
+fn config_path() -> Option<PathBuf> {
+    let config = match env::var_os("XDG_CONFIG_HOME") {
+        Some(config) if !config.is_empty() => PathBuf::from(config),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config.join("todo-rs").join("board"))
+}
+
+// is_header : Whether the line of a TODO file defines a column.
+pub fn is_header(line: &str) -> bool {
+    line.strip_prefix(HEADER_PREFIX)
+        .is_some_and(|line| line.starts_with(COLUMN_KEYWORD))
+}
+
+// header : The board defined by the header lines of a TODO file, if it has any.
+pub fn header(text: &str) -> Option<Result<Vec<Column>, String>> {
+    let lines: Vec<&str> = text
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix(HEADER_PREFIX)?
+                .strip_prefix(COLUMN_KEYWORD)
+        })
+        .collect();
+    (!lines.is_empty()).then(|| parse_columns(lines.into_iter()))
+}
+
+// load_config : The board of the config file, the default one without it.
+pub fn load_config() -> Result<Vec<Column>, String> {
+    let Some(path) = config_path() else {
+        return Ok(default_columns());
+    };
+    let config = match fs::read_to_string(&path) {
+        Ok(config) => config,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(default_columns()),
+        Err(error) => return Err(format!("could not read `{}`: {}", path.display(), error)),
+    };
+    let lines: Vec<&str> = config
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+    if lines.is_empty() {
+        return Ok(default_columns());
+    }
+    let mut definitions = Vec::new();
+    for line in lines {
+        match line.strip_prefix(COLUMN_KEYWORD) {
+            Some(definition) => definitions.push(definition),
+            None => {
+                return Err(format!(
+                    "{}: expected `column <name> ...`, found `{}`",
+                    path.display(),
+                    line
+                ))
+            }
+        }
+    }
+    parse_columns(definitions.into_iter())
+        .map_err(|reason| format!("{}: {}", path.display(), reason))
+}
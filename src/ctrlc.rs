@@ -13,7 +13,7 @@ pub fn init() {
     unsafe {
         // It uses the libc crate. Inside the function, 
         // it calls libc::signal with SIGINT and the callback function as the signal handler.
-        if libc::signal(libc::SIGINT, callback as libc::sighandler_t) == libc::SIG_ERR {
+        if libc::signal(libc::SIGINT, callback as *const () as libc::sighandler_t) == libc::SIG_ERR {
         
             // If libc::signal returns an error (libc::SIG_ERR), it calls unreachable!(), 
             // meaning that the code has entered an unreachable state.
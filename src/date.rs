@@ -0,0 +1,205 @@
+use std::fmt;
+
+// struct Date represents a calendar day, written in files as `YYYY-MM-DD`.
+// Field order matters: the derived Ord compares year, then month, then day.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+pub fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+impl Date {
+    // parse : Reads a `YYYY-MM-DD` string, rejecting days that do not exist.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.splitn(3, '-');
+        let year = parts.next()?;
+        let month = parts.next()?;
+        let day = parts.next()?;
+        let digits =
+            |part: &str, len| part.len() == len && part.bytes().all(|b| b.is_ascii_digit());
+        if !digits(year, 4) || !digits(month, 2) || !digits(day, 2) {
+            return None;
+        }
+        let date = Self {
+            year: year.parse().ok()?,
+            month: month.parse().ok()?,
+            day: day.parse().ok()?,
+        };
+        if date.day == 0 || date.day > days_in_month(date.year, date.month) {
+            return None;
+        }
+        Some(date)
+    }
+
+    // days : Number of days since 1970-01-01, negative before it.
+    // Uses the days_from_civil algorithm by Howard Hinnant.
+    pub fn days(&self) -> i64 {
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        } as i64;
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = self.month as i64;
+        let day_of_year =
+            (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146097 + day_of_era - 719468
+    }
+
+    // from_days : Inverse of `days`.
+    pub fn from_days(days: i64) -> Self {
+        let days = days + 719468;
+        let era = days.div_euclid(146097);
+        let day_of_era = days - era * 146097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u32;
+        let year = (year_of_era + era * 400) as i32 + if month <= 2 { 1 } else { 0 };
+        Self { year, month, day }
+    }
+
+    pub fn add_days(&self, days: i64) -> Self {
+        Self::from_days(self.days() + days)
+    }
+
+    // today : Returns the current day in the local timezone.
+    pub fn today() -> Self {
+        Self::from_timestamp(unsafe { libc::time(std::ptr::null_mut()) } as u64)
+    }
+
+    // from_timestamp : The local day of a time given in seconds since the Unix epoch.
+    pub fn from_timestamp(timestamp: u64) -> Self {
+        unsafe {
+            // libc::localtime_r fills a `tm` struct with the broken-down local time,
+            // its year counts from 1900 and its month from 0.
+            let time = timestamp as libc::time_t;
+            let mut tm: libc::tm = std::mem::zeroed();
+            libc::localtime_r(&time, &mut tm);
+            Self {
+                year: tm.tm_year + 1900,
+                month: (tm.tm_mon + 1) as u32,
+                day: tm.tm_mday as u32,
+            }
+        }
+    }
+
+    // timestamp : Inverse of `from_timestamp`, the local midnight starting the day.
+    pub fn timestamp(&self) -> u64 {
+        unsafe {
+            // libc::mktime reads the same `tm` struct, tm_isdst = -1 lets it figure out daylight saving.
+            let mut tm: libc::tm = std::mem::zeroed();
+            tm.tm_year = self.year - 1900;
+            tm.tm_mon = self.month as i32 - 1;
+            tm.tm_mday = self.day as i32;
+            tm.tm_isdst = -1;
+            libc::mktime(&mut tm).max(0) as u64
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> Date {
+        Date::parse(text).unwrap()
+    }
+
+    #[test]
+    fn parse_rejects_days_that_do_not_exist() {
+        assert_eq!(
+            date("2024-02-29"),
+            Date {
+                year: 2024,
+                month: 2,
+                day: 29
+            }
+        );
+        assert_eq!(Date::parse("2026-02-29"), None);
+        assert_eq!(Date::parse("1900-02-29"), None);
+        assert_eq!(Date::parse("2026-04-31"), None);
+        assert_eq!(Date::parse("2026-13-01"), None);
+        assert_eq!(Date::parse("2026-10-00"), None);
+    }
+
+    #[test]
+    fn parse_rejects_other_shapes() {
+        for text in [
+            "",
+            "2026",
+            "2026-10",
+            "2026-1-05",
+            "26-10-18",
+            "2026-10-18T10",
+            "+026-10-18",
+            "2026-+1-05",
+            "2026-10-+1",
+        ] {
+            assert_eq!(Date::parse(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn days_count_from_the_epoch() {
+        assert_eq!(date("1970-01-01").days(), 0);
+        assert_eq!(date("1969-12-31").days(), -1);
+        assert_eq!(date("2000-03-01").days(), 11017);
+        assert_eq!(date("2026-10-18").days(), 20744);
+    }
+
+    #[test]
+    fn from_days_is_the_inverse_of_days() {
+        let start = date("1899-12-25");
+        for days in 0..365 * 130 {
+            let day = start.add_days(days);
+            assert_eq!(Date::from_days(day.days()), day);
+            assert_eq!(day.add_days(1).days(), day.days() + 1);
+            assert!(day < day.add_days(1));
+        }
+        assert_eq!(date("2024-02-28").add_days(1), date("2024-02-29"));
+        assert_eq!(date("2026-12-31").add_days(1), date("2027-01-01"));
+        assert_eq!(date("2026-03-01").add_days(-1), date("2026-02-28"));
+    }
+
+    #[test]
+    fn display_pads_with_zeros() {
+        assert_eq!(date("0999-01-05").to_string(), "0999-01-05");
+    }
+
+    #[test]
+    fn timestamps_keep_the_local_day() {
+        let day = date("2026-03-29");
+        assert_eq!(Date::from_timestamp(day.timestamp()), day);
+        assert_eq!(Date::from_timestamp(day.timestamp() + 12 * 3600), day);
+    }
+}
@@ -0,0 +1,582 @@
+use crate::date::Date;
+use crate::item::{now, parse_tags, Item, DUE_KEY, ID_KEY, REC_KEY};
+use crate::recur::Recurrence;
+use crate::status::{Line, Status};
+use std::collections::HashSet;
+
+// Lines longer than this many bytes are folded onto continuation lines starting with a space.
+const FOLD_WIDTH: usize = 75;
+// UIDs of the items written by todo-rs, `<id>@todo-rs`. Items imported from other
+// clients keep their own UID in the metadata under UID_KEY.
+const UID_SUFFIX: &str = "@todo-rs";
+const UID_KEY: &str = "uid";
+// Other metadata is written as `X-TODO-RS-META:key:value`.
+const META_PROPERTY: &str = "X-TODO-RS-META";
+// RFC 5545 only has four statuses, items of the other columns (like BLOCKED) are
+// `NEEDS-ACTION` or `COMPLETED` with this property set to the prefix of their column.
+const STATUS_PROPERTY: &str = "X-TODO-RS-STATUS";
+
+// The STATUS value of each column.
+fn format_status(status: Status) -> &'static str {
+    match (status.is_closed(), status.prefix()) {
+        (false, "IN-PROGRESS") => "IN-PROCESS",
+        (false, _) => "NEEDS-ACTION",
+        (true, "CANCELLED") => "CANCELLED",
+        (true, _) => "COMPLETED",
+    }
+}
+
+// The column of a STATUS value, when STATUS_PROPERTY doesn't name one.
+fn parse_status(value: &str) -> Option<Status> {
+    match value {
+        "NEEDS-ACTION" => Some(Status::first()),
+        "IN-PROCESS" => Some(Status::find("IN-PROGRESS").unwrap_or(Status::first())),
+        "COMPLETED" => Some(Status::first_closed()),
+        "CANCELLED" => Some(Status::find("CANCELLED").unwrap_or(Status::first_closed())),
+        _ => None,
+    }
+}
+
+// Backslash escapes of TEXT values, RFC 5545 section 3.3.11.
+fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => unescaped.push('\n'),
+                Some(c) => unescaped.push(c),
+                None => {}
+            },
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+// `20261018T093000Z` in UTC.
+fn format_datetime(timestamp: u64) -> String {
+    let date = Date::from_days((timestamp / 86400) as i64);
+    let seconds = timestamp % 86400;
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        date.year,
+        date.month,
+        date.day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+fn parse_date(value: &str) -> Option<Date> {
+    let digits = value
+        .get(..8)
+        .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))?;
+    Date::parse(&format!(
+        "{}-{}-{}",
+        &digits[..4],
+        &digits[4..6],
+        &digits[6..]
+    ))
+}
+
+// Date-times are read as UTC, plain dates as the local midnight of the day.
+fn parse_datetime(value: &str) -> Option<u64> {
+    let date = parse_date(value)?;
+    match value.get(8..) {
+        Some("") => Some(date.timestamp()),
+        Some(time) => {
+            let time = time.strip_prefix('T')?.trim_end_matches('Z');
+            let hours: u64 = time.get(..2)?.parse().ok()?;
+            let minutes: u64 = time.get(2..4)?.parse().ok()?;
+            let seconds: u64 = time.get(4..6)?.parse().ok()?;
+            let days = u64::try_from(date.days()).ok()?;
+            Some(days * 86400 + hours * 3600 + minutes * 60 + seconds)
+        }
+        None => None,
+    }
+}
+
+// RFC 5545 priorities go from 1 (highest) to 9 (lowest), (A) to (I) map onto them.
+fn format_priority(priority: char) -> u32 {
+    (priority as u32 - 'A' as u32 + 1).min(9)
+}
+
+fn format_rrule(recurrence: Recurrence) -> String {
+    match recurrence {
+        Recurrence::Days(1) => "FREQ=DAILY".to_string(),
+        Recurrence::Days(7) => "FREQ=WEEKLY".to_string(),
+        Recurrence::Days(days) => format!("FREQ=DAILY;INTERVAL={}", days),
+        Recurrence::Monthly(None) => "FREQ=MONTHLY".to_string(),
+        Recurrence::Monthly(Some(day)) => format!("FREQ=MONTHLY;BYMONTHDAY={}", day),
+    }
+}
+
+// Inverse of `format_rrule`, other rules have no `rec:` equivalent.
+fn parse_rrule(rule: &str) -> Option<String> {
+    let parts: Vec<(&str, &str)> = rule
+        .split(';')
+        .filter_map(|part| part.split_once('='))
+        .collect();
+    let get = |name: &str| {
+        parts
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    };
+    let rule = match (
+        get("FREQ")?,
+        get("INTERVAL").unwrap_or("1"),
+        get("BYMONTHDAY"),
+    ) {
+        ("DAILY", "1", None) => "daily".to_string(),
+        ("WEEKLY", "1", None) => "weekly".to_string(),
+        ("DAILY", interval, None) => format!("{}d", interval),
+        ("WEEKLY", interval, None) => format!("{}d", interval.parse::<u32>().ok()?.checked_mul(7)?),
+        ("MONTHLY", "1", None) => "monthly".to_string(),
+        ("MONTHLY", "1", Some(day)) => format!("monthly-{}", day),
+        _ => return None,
+    };
+    Recurrence::parse(&rule).map(|_| rule)
+}
+
+// Splits a content line into its name, its parameters and its value.
+fn parse_property(line: &str) -> Option<(&str, &str, &str)> {
+    let (head, value) = line.split_once(':')?;
+    let (name, params) = head.split_once(';').unwrap_or((head, ""));
+    Some((name, params, value))
+}
+
+// Reads every VTODO of the calendar into the lists. Any other component, like VEVENT
+// or VTIMEZONE, and the properties of the calendar, like X-WR-CALNAME, are kept in `lines`
+// to be written back as they were. Components nested in a VTODO, like its VALARMs, are skipped.
+pub fn load(text: &str, lists: &mut [Vec<Item>], lines: &mut Vec<Line>) -> Result<(), String> {
+    // Unfold the continuation lines first.
+    let mut unfolded: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.trim_end_matches('\r');
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            unfolded.last_mut(),
+        ) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => unfolded.push(line.to_string()),
+        }
+    }
+
+    let mut uids: Vec<(String, usize)> = Vec::new(); // UID and depth of every item read so far
+    let mut component: Option<String> = None; // Name of the top level component being read
+    let mut nested = 0; // Depth of the components inside the VTODO being read
+    let mut item = Item::default();
+    let mut status: Option<Status> = None;
+    let mut column: Option<Status> = None;
+    let mut parent: Option<String> = None;
+    for line in unfolded.iter() {
+        let Some((name, params, value)) = parse_property(line) else {
+            if component.as_deref().is_some_and(|name| name != "VTODO") {
+                lines.push(Line::Raw(line.clone()));
+            }
+            continue;
+        };
+        match (component.as_deref(), name) {
+            (None, "BEGIN") if value == "VTODO" => {
+                component = Some(value.to_string());
+                nested = 0;
+                item = Item::default();
+                status = None;
+                column = None;
+                parent = None;
+            }
+            (None, "BEGIN" | "END") if value == "VCALENDAR" => {}
+            (None, "BEGIN") => {
+                component = Some(value.to_string());
+                lines.push(Line::Raw(line.clone()));
+            }
+            // VERSION and PRODID are written anew by `save`.
+            (None, "VERSION" | "PRODID") => {}
+            (None, _) => lines.push(Line::Raw(line.clone())),
+            (Some("VTODO"), "BEGIN") => nested += 1,
+            (Some("VTODO"), "END") if nested > 0 => nested -= 1,
+            (Some("VTODO"), _) if nested > 0 => {}
+            (Some("VTODO"), "END") if value == "VTODO" => {
+                component = None;
+                let uid = item_uid(&item);
+                if let Some((_, depth)) = parent
+                    .take()
+                    .and_then(|parent| uids.iter().find(|(uid, _)| *uid == parent))
+                {
+                    item.depth = depth + 1;
+                }
+                uids.push((uid, item.depth));
+                item.tags = parse_tags(&item.title);
+                // Without a STATUS, items with a completion time are done.
+                let status = match column.take().or(status) {
+                    Some(status) => status,
+                    None if item.completed.is_some() => Status::first_closed(),
+                    None => Status::first(),
+                };
+                lists[status.index()].push(std::mem::take(&mut item));
+            }
+            (Some("VTODO"), _) => match name {
+                "UID" => match value
+                    .strip_suffix(UID_SUFFIX)
+                    .and_then(|id| id.parse().ok())
+                {
+                    Some(id) => item.id = id,
+                    None => {
+                        item.meta.insert(UID_KEY.to_string(), value.to_string());
+                    }
+                },
+                "SUMMARY" => item.title = unescape(value),
+                "DESCRIPTION" => {
+                    item.notes = unescape(value)
+                        .lines()
+                        .map(|note| note.to_string())
+                        .collect()
+                }
+                "STATUS" => status = parse_status(value),
+                STATUS_PROPERTY => column = Status::find(value),
+                "PRIORITY" => {
+                    if let Ok(priority @ 1..=9) = value.parse::<u32>() {
+                        item.priority = char::from_u32('A' as u32 + priority - 1);
+                    }
+                }
+                "CREATED" => item.created = parse_datetime(value),
+                "COMPLETED" => item.completed = parse_datetime(value),
+                "DUE" => {
+                    if let Some(due) = parse_date(value) {
+                        item.meta.insert(DUE_KEY.to_string(), due.to_string());
+                    }
+                }
+                "RRULE" => {
+                    if let Some(rule) = parse_rrule(value) {
+                        item.meta.insert(REC_KEY.to_string(), rule);
+                    }
+                }
+                "RELATED-TO" if params.is_empty() || params.contains("RELTYPE=PARENT") => {
+                    parent = Some(value.to_string());
+                }
+                META_PROPERTY => {
+                    if let Some((key, value)) = unescape(value).split_once(':') {
+                        match (key, value.parse()) {
+                            (ID_KEY, Ok(id)) => item.id = id,
+                            _ => {
+                                item.meta.insert(key.to_string(), value.to_string());
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            },
+            (Some(current), "END") if value == current => {
+                component = None;
+                lines.push(Line::Raw(line.clone()));
+            }
+            (Some(_), _) => lines.push(Line::Raw(line.clone())),
+        }
+    }
+    if component.is_some() {
+        return Err("the calendar ended in the middle of a component".to_string());
+    }
+    Ok(())
+}
+
+// Appends a content line to the calendar, folded to FOLD_WIDTH bytes.
+fn push_line(calendar: &mut String, line: &str) {
+    let mut start = 0;
+    let mut width = FOLD_WIDTH;
+    for (index, c) in line.char_indices() {
+        if index + c.len_utf8() - start > width {
+            calendar.push_str(&line[start..index]);
+            calendar.push_str("\r\n ");
+            start = index;
+            width = FOLD_WIDTH - 1;
+        }
+    }
+    calendar.push_str(&line[start..]);
+    calendar.push_str("\r\n");
+}
+
+fn item_uid(item: &Item) -> String {
+    match item.meta.get(UID_KEY) {
+        Some(uid) => uid.clone(),
+        None => format!("{}{}", item.id, UID_SUFFIX),
+    }
+}
+
+// Items with the UID of another client keep their id in a META_PROPERTY when other items wait for them.
+fn push_item(
+    calendar: &mut String,
+    status: Status,
+    list: &[Item],
+    index: usize,
+    referenced: &HashSet<u64>,
+) {
+    let with_id;
+    let mut item = &list[index];
+    if item.meta.contains_key(UID_KEY) && referenced.contains(&item.id) {
+        with_id = item.with_id();
+        item = &with_id;
+    }
+    let stamp = format_datetime(now());
+    push_line(calendar, "BEGIN:VTODO");
+    push_line(calendar, &format!("UID:{}", item_uid(item)));
+    push_line(calendar, &format!("DTSTAMP:{}", stamp));
+    push_line(calendar, &format!("SUMMARY:{}", escape(&item.title)));
+    push_line(calendar, &format!("STATUS:{}", format_status(status)));
+    if parse_status(format_status(status)) != Some(status) {
+        push_line(
+            calendar,
+            &format!("{}:{}", STATUS_PROPERTY, status.prefix()),
+        );
+    }
+    if let Some(priority) = item.priority {
+        push_line(calendar, &format!("PRIORITY:{}", format_priority(priority)));
+    }
+    if let Some(created) = item.created {
+        push_line(calendar, &format!("CREATED:{}", format_datetime(created)));
+    }
+    if let Some(completed) = item.completed {
+        push_line(
+            calendar,
+            &format!("COMPLETED:{}", format_datetime(completed)),
+        );
+    }
+    if let Some(due) = item.due() {
+        push_line(
+            calendar,
+            &format!(
+                "DUE;VALUE=DATE:{:04}{:02}{:02}",
+                due.year, due.month, due.day
+            ),
+        );
+    }
+    if let Some(recurrence) = item.recurrence() {
+        push_line(calendar, &format!("RRULE:{}", format_rrule(recurrence)));
+    }
+    if !item.notes.is_empty() {
+        push_line(
+            calendar,
+            &format!("DESCRIPTION:{}", escape(&item.notes.join("\n"))),
+        );
+    }
+    let parent = (0..index)
+        .rev()
+        .find(|&parent| list[parent].depth < item.depth);
+    if let Some(parent) = parent {
+        push_line(
+            calendar,
+            &format!("RELATED-TO;RELTYPE=PARENT:{}", item_uid(&list[parent])),
+        );
+    }
+    for (key, value) in item.meta.iter() {
+        if key != DUE_KEY && key != REC_KEY && key != UID_KEY {
+            push_line(
+                calendar,
+                &format!(
+                    "{}:{}",
+                    META_PROPERTY,
+                    escape(&format!("{}:{}", key, value))
+                ),
+            );
+        }
+    }
+    push_line(calendar, "END:VTODO");
+}
+
+pub fn save(lists: &[Vec<Item>], lines: &[Line]) -> String {
+    let mut calendar = String::new();
+    push_line(&mut calendar, "BEGIN:VCALENDAR");
+    push_line(&mut calendar, "VERSION:2.0");
+    push_line(&mut calendar, "PRODID:-//todo-rs//todo-rs//EN");
+    let referenced: HashSet<u64> = lists
+        .iter()
+        .flatten()
+        .flat_map(Item::prerequisites)
+        .collect();
+    // The kept properties of the calendar go before any component, the kept components after the items.
+    let mut depth = 0;
+    let mut properties = Vec::new();
+    let mut components = Vec::new();
+    for line in lines.iter() {
+        let Line::Raw(text) = line else {
+            continue;
+        };
+        match parse_property(text).map(|(name, _, _)| name) {
+            Some("BEGIN") => depth += 1,
+            Some("END") => depth -= 1,
+            _ if depth == 0 => {
+                properties.push(text);
+                continue;
+            }
+            _ => {}
+        }
+        components.push(text);
+    }
+    for text in properties {
+        push_line(&mut calendar, text);
+    }
+    for status in Status::all() {
+        let list = &lists[status.index()];
+        for index in 0..list.len() {
+            push_item(&mut calendar, status, list, index, &referenced);
+        }
+    }
+    for text in components {
+        push_line(&mut calendar, text);
+    }
+    push_line(&mut calendar, "END:VCALENDAR");
+    calendar
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::new_lists;
+
+    fn load_text(text: &str) -> (Vec<Vec<Item>>, Vec<Line>) {
+        let mut lists = new_lists();
+        let mut lines = Vec::new();
+        load(text, &mut lists, &mut lines).unwrap();
+        (lists, lines)
+    }
+
+    #[test]
+    fn text_escapes() {
+        let text = "a\\b;c,d\ne";
+        assert_eq!(escape(text), r"a\\b\;c\,d\ne");
+        assert_eq!(unescape(&escape(text)), text);
+        assert_eq!(unescape("line\\Nbreak\\"), "line\nbreak");
+    }
+
+    #[test]
+    fn folds_long_lines_between_characters() {
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+        let mut calendar = String::new();
+        push_line(&mut calendar, &line);
+        assert!(calendar
+            .split("\r\n")
+            .all(|folded| folded.len() <= FOLD_WIDTH));
+        let (lists, _) = load_text(&format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\n{}END:VTODO\r\nEND:VCALENDAR\r\n",
+            calendar
+        ));
+        assert_eq!(lists[Status::first().index()][0].title, "é".repeat(60));
+    }
+
+    #[test]
+    fn reads_dates() {
+        assert_eq!(parse_date("20261018"), Date::parse("2026-10-18"));
+        assert_eq!(parse_date("20261018T093000Z"), Date::parse("2026-10-18"));
+        assert_eq!(parse_date("20260230"), None);
+        assert_eq!(parse_date("2026101"), None);
+        assert_eq!(parse_date("ab€xyz12"), None);
+        assert_eq!(parse_date("2026+101"), None);
+        let noon = Date::parse("2026-10-18").unwrap().days() as u64 * 86400 + 12 * 3600;
+        assert_eq!(parse_datetime("20261018T120000Z"), Some(noon));
+        assert_eq!(format_datetime(noon), "20261018T120000Z");
+        assert_eq!(parse_datetime("20261018T12"), None);
+    }
+
+    #[test]
+    fn reads_rules() {
+        assert_eq!(
+            parse_rrule("FREQ=WEEKLY;INTERVAL=2").as_deref(),
+            Some("14d")
+        );
+        assert_eq!(
+            parse_rrule("FREQ=MONTHLY;BYMONTHDAY=31").as_deref(),
+            Some("monthly-31")
+        );
+        assert_eq!(parse_rrule("FREQ=YEARLY"), None);
+        assert_eq!(parse_rrule("FREQ=DAILY;INTERVAL=0"), None);
+        assert_eq!(parse_rrule("FREQ=WEEKLY;INTERVAL=999999999"), None);
+        for rule in ["daily", "weekly", "3d", "monthly", "monthly-15"] {
+            let recurrence = Recurrence::parse(rule).unwrap();
+            assert_eq!(
+                parse_rrule(&format_rrule(recurrence)).as_deref(),
+                Some(rule)
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_other_components_and_skips_nested_ones() {
+        let text = "BEGIN:VCALENDAR\r\n\
+                    VERSION:2.0\r\n\
+                    X-WR-CALNAME:Work\r\n\
+                    BEGIN:VTODO\r\n\
+                    UID:abc@example.com\r\n\
+                    SUMMARY:Ship it\r\n\
+                    DESCRIPTION:first\\nsecond\r\n\
+                    STATUS:IN-PROCESS\r\n\
+                    DUE;VALUE=DATE:20261020\r\n\
+                    BEGIN:VALARM\r\n\
+                    DESCRIPTION:Reminder\r\n\
+                    END:VALARM\r\n\
+                    END:VTODO\r\n\
+                    BEGIN:VEVENT\r\n\
+                    SUMMARY:Meeting\r\n\
+                    END:VEVENT\r\n\
+                    END:VCALENDAR\r\n";
+        let (lists, lines) = load_text(text);
+        let item = &lists[Status::find("IN-PROGRESS").unwrap().index()][0];
+        assert_eq!(item.title, "Ship it");
+        assert_eq!(item.notes, ["first", "second"]);
+        assert_eq!(
+            item.meta.get(DUE_KEY).map(String::as_str),
+            Some("2026-10-20")
+        );
+        assert_eq!(
+            item.meta.get(UID_KEY).map(String::as_str),
+            Some("abc@example.com")
+        );
+        let raw: Vec<&str> = lines
+            .iter()
+            .filter_map(|line| match line {
+                Line::Raw(text) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            raw,
+            [
+                "X-WR-CALNAME:Work",
+                "BEGIN:VEVENT",
+                "SUMMARY:Meeting",
+                "END:VEVENT"
+            ]
+        );
+
+        let saved = save(&lists, &lines);
+        let (again, again_lines) = load_text(&saved);
+        assert_eq!(again, lists);
+        assert_eq!(again_lines, lines);
+    }
+
+    #[test]
+    fn rejects_unterminated_components() {
+        let mut lines = Vec::new();
+        assert!(load(
+            "BEGIN:VCALENDAR\r\nBEGIN:VTODO\r\nSUMMARY:x\r\n",
+            &mut new_lists(),
+            &mut lines
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,379 @@
+use crate::date::Date;
+use crate::recur::Recurrence;
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Keys of the `key:value` tokens that are stored in dedicated Item fields
+// instead of the free-form metadata map.
+pub const ID_KEY: &str = "id";
+pub const CREATED_KEY: &str = "created";
+pub const COMPLETED_KEY: &str = "completed";
+
+// Keys of the metadata map that the app itself understands.
+pub const DUE_KEY: &str = "due";
+pub const REC_KEY: &str = "rec";
+// Seconds tracked on the item by its timer, and the time the running timer was started.
+const SPENT_KEY: &str = "spent";
+const STARTED_KEY: &str = "started";
+// Ids of the items that have to be closed before this one, `after:3,5`.
+const AFTER_KEY: &str = "after";
+
+// Lowest priority that can be given with the priority keys, `(A)` is the highest.
+const LOWEST_PRIORITY: char = 'C';
+
+// Characters that follow a backslash in the escapes of a title written to a file:
+//      `\\` a backslash, `\n`, `\r` and `\t` a newline, carriage return and tab,
+//      `\s` a space at the start or the end of the title (editors tend to trim them),
+//      `\(` a leading `(` that would read as a priority, `\:` in a last word
+//      that would read as a `key:value` token.
+// Other backslashes are kept as they are, so `\*` in a Markdown title stays `\*`.
+const ESCAPES: [char; 7] = ['\\', 'n', 'r', 't', 's', '(', ':'];
+
+// struct Item represents a single entry of the TODO or DONE list.
+// `id` is stable across saves, timestamps are seconds since the Unix epoch
+// and `meta` keeps any other `key:value` tokens found at the end of the line.
+// `priority` is the letter of a todo.txt style `(A)` marker in front of the title
+// and `tags` are the `+project` and `@context` words of the title.
+// `depth` is the nesting level, subtasks come right after their parent one level deeper.
+// `notes` are free-form lines of text attached to the item.
+// `indent` is the whitespace in front of the planning line and the property drawer of an
+// org-mode headline, kept as it was read. The other formats leave it empty.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Item {
+    pub id: u64,
+    pub depth: usize,
+    pub indent: String,
+    pub priority: Option<char>,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub notes: Vec<String>,
+    pub created: Option<u64>,
+    pub completed: Option<u64>,
+    pub meta: BTreeMap<String, String>,
+}
+
+// Returns the current time as seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+// Collects the `+project` and `@context` words of a title.
+pub fn parse_tags(title: &str) -> Vec<String> {
+    title
+        .split_whitespace()
+        .filter(|word| word.len() > 1 && (word.starts_with('+') || word.starts_with('@')))
+        .map(|word| word.to_string())
+        .collect()
+}
+
+// escape_title : Writes a title on one line so that `Item::parse` reads it back as it was.
+pub fn escape_title(title: &str) -> String {
+    let leading = title.len() - title.trim_start_matches(' ').len();
+    let trailing = title.len() - title.trim_end_matches(' ').len();
+    let mut pieces: Vec<String> = Vec::new();
+    for (index, c) in title.char_indices() {
+        let piece = match c {
+            ' ' if index < leading || index >= title.len() - trailing => "\\s".to_string(),
+            '\n' => "\\n".to_string(),
+            '\r' => "\\r".to_string(),
+            '\t' => "\\t".to_string(),
+            c => c.to_string(),
+        };
+        pieces.push(piece);
+    }
+    // A backslash is only doubled when what follows it would read as an escape.
+    let mut escaped = String::new();
+    for (index, piece) in pieces.iter().enumerate() {
+        escaped.push_str(piece);
+        let next = pieces.get(index + 1).and_then(|next| next.chars().next());
+        if piece == "\\" && next.is_none_or(|next| ESCAPES.contains(&next)) {
+            escaped.push('\\');
+        }
+    }
+    if let [b'(', b'A'..=b'Z', b')', ..] = escaped.as_bytes() {
+        escaped.insert(0, '\\');
+    }
+    let last = escaped.rsplit(' ').next().unwrap_or("");
+    if let (Some(_), Some(colon)) = (parse_meta(last), last.find(':')) {
+        escaped.insert(escaped.len() - last.len() + colon, '\\');
+    }
+    escaped
+}
+
+// Inverse of `escape_title`.
+pub fn unescape_title(text: &str) -> String {
+    let mut title = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let escape = chars
+            .peek()
+            .copied()
+            .filter(|next| c == '\\' && ESCAPES.contains(next));
+        match escape {
+            Some(next) => {
+                chars.next();
+                title.push(match next {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    's' => ' ',
+                    next => next,
+                });
+            }
+            None => title.push(c),
+        }
+    }
+    title
+}
+
+// Splits a `key:value` token. Values starting with '/' are rejected
+// so that URLs like `http://example.com` stay part of the title.
+pub fn parse_meta(token: &str) -> Option<(&str, &str)> {
+    let (key, value) = token.split_once(':')?;
+    let mut chars = key.chars();
+    let key_ok = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if key_ok && !value.is_empty() && !value.starts_with('/') {
+        Some((key, value))
+    } else {
+        None
+    }
+}
+
+impl Item {
+    // new : Creates a fresh item with the given id, stamped with the current time.
+    pub fn new(id: u64, title: &str) -> Self {
+        Self {
+            id,
+            title: title.to_string(),
+            created: Some(now()),
+            ..Self::default()
+        }
+    }
+
+    // parse : Builds an item from the text that follows the `TODO: `/`DONE: ` prefix.
+    // A leading `(A)`..`(Z)` marker becomes the priority, trailing `key:value` tokens
+    // are peeled off into fields and metadata, everything in between is the title,
+    // with its escapes read (see ESCAPES).
+    // A missing id is left as 0.
+    pub fn parse(text: &str) -> Self {
+        let mut item = Self::parse_raw(text);
+        item.title = unescape_title(&item.title);
+        item.tags = parse_tags(&item.title);
+        item
+    }
+
+    // parse_raw : Like `parse` but the title is kept as it was typed, backslashes included.
+    pub fn parse_raw(text: &str) -> Self {
+        let mut item = Self::default();
+        let mut title = text;
+        if let [b'(', letter @ b'A'..=b'Z', b')', b' ', ..] = text.as_bytes() {
+            item.priority = Some(*letter as char);
+            title = &text[4..];
+        }
+        while let Some((rest, token)) = title.rsplit_once(' ') {
+            let Some((key, value)) = parse_meta(token) else {
+                break;
+            };
+            item.set_meta(key, value);
+            title = rest;
+        }
+        item.title = title.to_string();
+        item.tags = parse_tags(title);
+        item
+    }
+
+    // set_meta : Reads a `key:value` token into the field it names, or into the metadata.
+    // Ids and timestamps that are not numbers, like the ids of other todo.txt tools,
+    // are kept as metadata.
+    pub fn set_meta(&mut self, key: &str, value: &str) {
+        match (key, value.parse()) {
+            (ID_KEY, Ok(id)) => self.id = id,
+            (CREATED_KEY, Ok(created)) => self.created = Some(created),
+            (COMPLETED_KEY, Ok(completed)) => self.completed = Some(completed),
+            _ => {
+                self.meta.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+
+    // retitle : Moves `key:value` tokens typed at the end of the title into the metadata,
+    // so that renaming an item to `Ship it due:2026-10-20` sets its due date.
+    pub fn retitle(&mut self) {
+        let parsed = Self::parse_raw(&self.title);
+        self.priority = parsed.priority.or(self.priority);
+        self.title = parsed.title;
+        self.tags = parsed.tags;
+        self.meta.extend(parsed.meta);
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|item_tag| item_tag == tag)
+    }
+
+    // due : The due date of the item, if it has a valid one.
+    pub fn due(&self) -> Option<Date> {
+        self.meta.get(DUE_KEY).and_then(|due| Date::parse(due))
+    }
+
+    // raise_priority : None -> (C) -> (B) -> (A), staying at (A).
+    pub fn raise_priority(&mut self) {
+        self.priority = match self.priority {
+            Some('A') => Some('A'),
+            Some(letter) => Some((letter as u8 - 1) as char),
+            None => Some(LOWEST_PRIORITY),
+        };
+    }
+
+    // lower_priority : (A) -> (B) -> (C) -> None. Letters below (C) are dropped as well.
+    pub fn lower_priority(&mut self) {
+        self.priority = match self.priority {
+            Some(letter) if letter < LOWEST_PRIORITY => Some((letter as u8 + 1) as char),
+            _ => None,
+        };
+    }
+
+    // is_timing : Whether the timer of the item is running.
+    pub fn is_timing(&self) -> bool {
+        self.meta.contains_key(STARTED_KEY)
+    }
+
+    // spent : Seconds tracked on the item, including the running timer up to `now`.
+    pub fn spent(&self, now: u64) -> u64 {
+        let spent = self
+            .meta
+            .get(SPENT_KEY)
+            .and_then(|spent| spent.parse().ok())
+            .unwrap_or(0);
+        let started = self
+            .meta
+            .get(STARTED_KEY)
+            .and_then(|started| started.parse().ok());
+        spent + started.map_or(0, |started: u64| now.saturating_sub(started))
+    }
+
+    pub fn start_timer(&mut self, now: u64) {
+        if !self.is_timing() {
+            self.meta.insert(STARTED_KEY.to_string(), now.to_string());
+        }
+    }
+
+    // stop_timer : Adds the time of the running timer to the tracked time.
+    pub fn stop_timer(&mut self, now: u64) {
+        if self.is_timing() {
+            let spent = self.spent(now);
+            self.meta.remove(STARTED_KEY);
+            self.meta.insert(SPENT_KEY.to_string(), spent.to_string());
+        }
+    }
+
+    // prerequisites : Ids of the items this one waits for.
+    pub fn prerequisites(&self) -> Vec<u64> {
+        self.meta.get(AFTER_KEY).map_or(Vec::new(), |ids| {
+            ids.split(',').filter_map(|id| id.parse().ok()).collect()
+        })
+    }
+
+    pub fn set_prerequisites(&mut self, ids: &[u64]) {
+        if ids.is_empty() {
+            self.meta.remove(AFTER_KEY);
+        } else {
+            let ids: Vec<String> = ids.iter().map(u64::to_string).collect();
+            self.meta.insert(AFTER_KEY.to_string(), ids.join(","));
+        }
+    }
+
+    // with_id : A copy of the item that carries its id in the metadata, for the formats that
+    // leave ids out but have to keep the ones other items wait for.
+    pub fn with_id(&self) -> Self {
+        let mut item = self.clone();
+        item.meta.insert(ID_KEY.to_string(), self.id.to_string());
+        item
+    }
+
+    // recurrence : The repeat rule of the item, if it has a valid one.
+    pub fn recurrence(&self) -> Option<Recurrence> {
+        self.meta
+            .get(REC_KEY)
+            .and_then(|rule| Recurrence::parse(rule))
+    }
+
+    // next_occurrence : For a repeating item, a fresh copy due on the next occurrence
+    // after its due date that is later than today. Items without a due date repeat from today.
+    pub fn next_occurrence(&self, id: u64, today: Date) -> Option<Self> {
+        let start = self.due().unwrap_or(today);
+        // `monthly` sticks to the day of the start date, even after a shorter month.
+        let rule = self.recurrence()?;
+        let recurrence = match rule {
+            Recurrence::Monthly(None) => Recurrence::Monthly(Some(start.day)),
+            recurrence => recurrence,
+        };
+        let mut due = recurrence.next(start);
+        while due <= today {
+            due = recurrence.next(due);
+        }
+        let mut item = Self {
+            id,
+            created: Some(now()),
+            completed: None,
+            ..self.clone()
+        };
+        item.meta.insert(DUE_KEY.to_string(), due.to_string());
+        // A due date moved back by a shorter month would lose the day, the rule keeps it instead.
+        if rule == Recurrence::Monthly(None) && due.day != start.day {
+            item.meta
+                .insert(REC_KEY.to_string(), format!("monthly-{}", start.day));
+        }
+        item.meta.remove(SPENT_KEY);
+        item.meta.remove(STARTED_KEY);
+        Some(item)
+    }
+
+    // format : Inverse of `parse`, renders the priority and title followed by all the `key:value` tokens.
+    // A missing id, 0, is left out.
+    pub fn format(&self) -> String {
+        let mut line = self.format_plain();
+        if self.id != 0 {
+            line.push_str(&format!(" {}:{}", ID_KEY, self.id));
+        }
+        if let Some(created) = self.created {
+            line.push_str(&format!(" {}:{}", CREATED_KEY, created));
+        }
+        if let Some(completed) = self.completed {
+            line.push_str(&format!(" {}:{}", COMPLETED_KEY, completed));
+        }
+        line
+    }
+
+    // format_plain : Like `format` but without the id and the timestamps,
+    // for files that are mostly read by people or other tools.
+    pub fn format_plain(&self) -> String {
+        let title = escape_title(&self.title);
+        let mut line = match self.priority {
+            Some(letter) => format!("({}) {}", letter, title),
+            None => title,
+        };
+        for (key, value) in self.meta.iter() {
+            line.push_str(&format!(" {}:{}", key, value));
+        }
+        line
+    }
+}
+
+// Tracked time as `H:MM:SS`.
+pub fn format_duration(seconds: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// Returns an id that is not used by any of the given items.
+pub fn next_id<'a>(items: impl IntoIterator<Item = &'a Item>) -> u64 {
+    items.into_iter().map(|item| item.id).max().unwrap_or(0) + 1
+}
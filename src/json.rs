@@ -0,0 +1,465 @@
+use crate::item::{parse_tags, Item};
+use crate::status::Status;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+// enum Json is a parsed JSON value. Objects keep their keys in order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields
+                .iter()
+                .find(|(field, _)| field == key)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        match self {
+            Json::Number(number) if *number >= 0.0 && number.fract() == 0.0 => Some(*number as u64),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(text) => Some(text),
+            _ => None,
+        }
+    }
+}
+
+// struct Parser is a recursive descent parser over the characters of the text.
+// Errors are reported with the line number they were found on.
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("line {}: {}", self.line, message))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next();
+        if c == Some('\n') {
+            self.line += 1;
+        }
+        c
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(' ' | '\t' | '\n' | '\r') = self.chars.peek() {
+            self.next();
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => self.error(&format!("expected `{}` but found `{}`", expected, c)),
+            None => self.error(&format!("expected `{}` but the file ended", expected)),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Json) -> Result<Json, String> {
+        for expected in keyword.chars() {
+            if self.chars.peek() != Some(&expected) {
+                return self.error(&format!("expected `{}`", keyword));
+            }
+            self.next();
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some('n') => self.keyword("null", Json::Null),
+            Some('t') => self.keyword("true", Json::Bool(true)),
+            Some('f') => self.keyword("false", Json::Bool(false)),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('[') => self.array(),
+            Some('{') => self.object(),
+            Some('-' | '0'..='9') => self.number(),
+            Some(c) => {
+                let c = *c;
+                self.error(&format!("unexpected `{}`", c))
+            }
+            None => self.error("unexpected end of the file"),
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let mut text = String::new();
+        while let Some(c @ ('-' | '+' | '.' | 'e' | 'E' | '0'..='9')) = self.chars.peek() {
+            text.push(*c);
+            self.next();
+        }
+        match text.parse() {
+            Ok(number) => Ok(Json::Number(number)),
+            Err(_) => self.error(&format!("invalid number `{}`", text)),
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let mut code = 0;
+        for _ in 0..4 {
+            match self.next().and_then(|c| c.to_digit(16)) {
+                Some(digit) => code = code * 16 + digit,
+                None => return self.error("invalid `\\u` escape"),
+            }
+        }
+        Ok(code)
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut text = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(text),
+                Some('\\') => match self.next() {
+                    Some('"') => text.push('"'),
+                    Some('\\') => text.push('\\'),
+                    Some('/') => text.push('/'),
+                    Some('b') => text.push('\u{8}'),
+                    Some('f') => text.push('\u{c}'),
+                    Some('n') => text.push('\n'),
+                    Some('r') => text.push('\r'),
+                    Some('t') => text.push('\t'),
+                    Some('u') => {
+                        let mut code = self.hex4()?;
+                        // Characters outside the Basic Multilingual Plane come as a surrogate pair.
+                        if (0xD800..0xDC00).contains(&code) {
+                            if self.next() != Some('\\') || self.next() != Some('u') {
+                                return self.error("unpaired surrogate in `\\u` escape");
+                            }
+                            let low = self.hex4()?;
+                            if !(0xDC00..0xE000).contains(&low) {
+                                return self.error("unpaired surrogate in `\\u` escape");
+                            }
+                            code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                        }
+                        match char::from_u32(code) {
+                            Some(c) => text.push(c),
+                            None => return self.error("invalid `\\u` escape"),
+                        }
+                    }
+                    _ => return self.error("invalid escape in string"),
+                },
+                Some(c) => text.push(c),
+                None => return self.error("unterminated string"),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&']') {
+            self.next();
+            return Ok(Json::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some(']') => return Ok(Json::Array(values)),
+                _ => return self.error("expected `,` or `]`"),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut fields = Vec::new();
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&'}') {
+            self.next();
+            return Ok(Json::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            fields.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => {}
+                Some('}') => return Ok(Json::Object(fields)),
+                _ => return self.error("expected `,` or `}`"),
+            }
+        }
+    }
+}
+
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut parser = Parser {
+        chars: text.chars().peekable(),
+        line: 1,
+    };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    match parser.next() {
+        Some(_) => parser.error("unexpected text after the end of the document"),
+        None => Ok(value),
+    }
+}
+
+fn write_string(out: &mut String, text: &str) {
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// Writes the value indented by two spaces per level, empty arrays and objects stay on one line.
+fn write_value(out: &mut String, value: &Json, level: usize) {
+    let indent = "  ".repeat(level + 1);
+    match value {
+        Json::Null => out.push_str("null"),
+        Json::Bool(value) => write!(out, "{}", value).unwrap(),
+        Json::Number(number) => write!(out, "{}", number).unwrap(),
+        Json::String(text) => write_string(out, text),
+        Json::Array(values) if values.is_empty() => out.push_str("[]"),
+        Json::Array(values) => {
+            out.push('[');
+            for (index, value) in values.iter().enumerate() {
+                out.push_str(if index == 0 { "\n" } else { ",\n" });
+                out.push_str(&indent);
+                write_value(out, value, level + 1);
+            }
+            write!(out, "\n{}]", "  ".repeat(level)).unwrap();
+        }
+        Json::Object(fields) if fields.is_empty() => out.push_str("{}"),
+        Json::Object(fields) => {
+            out.push('{');
+            for (index, (key, value)) in fields.iter().enumerate() {
+                out.push_str(if index == 0 { "\n" } else { ",\n" });
+                out.push_str(&indent);
+                write_string(out, key);
+                out.push_str(": ");
+                write_value(out, value, level + 1);
+            }
+            write!(out, "\n{}}}", "  ".repeat(level)).unwrap();
+        }
+    }
+}
+
+pub fn to_string(value: &Json) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, 0);
+    out.push('\n');
+    out
+}
+
+fn optional_number(value: Option<u64>) -> Json {
+    value.map_or(Json::Null, |value| Json::Number(value as f64))
+}
+
+// The tags are derived from the title, they are written for scripts but ignored when reading.
+fn item_to_json(item: &Item) -> Json {
+    let strings = |list: &[String]| Json::Array(list.iter().cloned().map(Json::String).collect());
+    Json::Object(vec![
+        ("id".to_string(), Json::Number(item.id as f64)),
+        ("title".to_string(), Json::String(item.title.clone())),
+        (
+            "priority".to_string(),
+            item.priority
+                .map_or(Json::Null, |priority| Json::String(priority.to_string())),
+        ),
+        ("depth".to_string(), Json::Number(item.depth as f64)),
+        ("created".to_string(), optional_number(item.created)),
+        ("completed".to_string(), optional_number(item.completed)),
+        ("tags".to_string(), strings(&item.tags)),
+        ("notes".to_string(), strings(&item.notes)),
+        (
+            "meta".to_string(),
+            Json::Object(
+                item.meta
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Json::String(value.clone())))
+                    .collect(),
+            ),
+        ),
+    ])
+}
+
+fn item_from_json(value: &Json) -> Result<Item, String> {
+    let title = value
+        .get("title")
+        .and_then(Json::as_str)
+        .ok_or("item without a `title` string")?;
+    let mut item = Item {
+        title: title.to_string(),
+        tags: parse_tags(title),
+        ..Item::default()
+    };
+    item.id = value.get("id").and_then(Json::as_u64).unwrap_or(0);
+    item.depth = value.get("depth").and_then(Json::as_u64).unwrap_or(0) as usize;
+    item.created = value.get("created").and_then(Json::as_u64);
+    item.completed = value.get("completed").and_then(Json::as_u64);
+    item.priority = value
+        .get("priority")
+        .and_then(Json::as_str)
+        .and_then(|priority| priority.chars().next())
+        .filter(|priority| priority.is_ascii_uppercase());
+    if let Some(Json::Array(notes)) = value.get("notes") {
+        item.notes = notes
+            .iter()
+            .filter_map(Json::as_str)
+            .map(|note| note.to_string())
+            .collect();
+    }
+    if let Some(Json::Object(meta)) = value.get("meta") {
+        let meta: BTreeMap<String, String> = meta
+            .iter()
+            .filter_map(|(key, value)| Some((key.clone(), value.as_str()?.to_string())))
+            .collect();
+        item.meta.extend(meta);
+    }
+    Ok(item)
+}
+
+// The whole file is one object with a list per column, keyed by its prefix in lowercase,
+// `{"todo": [...], "in-progress": [...], ...}`. Lists of columns the board doesn't have are
+// refused rather than dropped.
+pub fn load(text: &str, lists: &mut [Vec<Item>]) -> Result<(), String> {
+    let document = parse(text)?;
+    let Json::Object(fields) = &document else {
+        return Err("the file is not a JSON object".to_string());
+    };
+    for (key, value) in fields.iter() {
+        let Some(status) = Status::all().find(|status| status.prefix().to_lowercase() == *key)
+        else {
+            return Err(format!("`{}` is not a column of the board", key));
+        };
+        match value {
+            Json::Array(items) => {
+                for item in items.iter() {
+                    lists[status.index()].push(item_from_json(item)?);
+                }
+            }
+            _ => return Err(format!("`{}` is not an array", key)),
+        }
+    }
+    Ok(())
+}
+
+pub fn save(lists: &[Vec<Item>]) -> String {
+    to_string(&Json::Object(
+        Status::all()
+            .map(|status| {
+                let items = lists[status.index()].iter().map(item_to_json).collect();
+                (status.prefix().to_lowercase(), Json::Array(items))
+            })
+            .collect(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> Result<String, String> {
+        match parse(text)? {
+            Json::String(text) => Ok(text),
+            value => panic!("not a string: {:?}", value),
+        }
+    }
+
+    #[test]
+    fn reads_escapes() {
+        assert_eq!(
+            string(r#""a\"b\\c\/d\n\r\t\b\f""#).unwrap(),
+            "a\"b\\c/d\n\r\t\u{8}\u{c}"
+        );
+        assert_eq!(string(r#""é€""#).unwrap(), "é€");
+        assert_eq!(string(r#""😀""#).unwrap(), "😀");
+        assert!(string(r#""\x""#).is_err());
+        assert!(string(r#""\u12""#).is_err());
+        assert!(string(r#""\u12G4""#).is_err());
+    }
+
+    #[test]
+    fn rejects_unpaired_surrogates() {
+        assert!(string(r#""\uD83D""#).is_err());
+        assert!(string(r#""\uD83Dx""#).is_err());
+        assert!(string(r#""\uD83DA""#).is_err());
+        assert!(string(r#""\uD83D\uD83D""#).is_err());
+        assert!(string(r#""\uDE00""#).is_err());
+    }
+
+    #[test]
+    fn reads_nested_values() {
+        let value = parse(" {\"a\": [1, -2.5e1, true, null], \"b\": {}, \"c\": []}\n").unwrap();
+        assert_eq!(
+            value,
+            Json::Object(vec![
+                (
+                    "a".to_string(),
+                    Json::Array(vec![
+                        Json::Number(1.0),
+                        Json::Number(-25.0),
+                        Json::Bool(true),
+                        Json::Null
+                    ])
+                ),
+                ("b".to_string(), Json::Object(Vec::new())),
+                ("c".to_string(), Json::Array(Vec::new())),
+            ])
+        );
+        assert_eq!(parse(&to_string(&value)).unwrap(), value);
+    }
+
+    #[test]
+    fn reports_bad_input_with_its_line() {
+        assert_eq!(
+            parse("{\n\"a\": tru\n}"),
+            Err("line 2: expected `true`".to_string())
+        );
+        assert_eq!(
+            parse("[1,\n2"),
+            Err("line 2: expected `,` or `]`".to_string())
+        );
+        assert!(parse("").is_err());
+        assert!(parse("\"unterminated").is_err());
+        assert!(parse("{\"a\" 1}").is_err());
+        assert!(parse("[1,]").is_err());
+        assert!(parse("1 2").is_err());
+        assert!(parse("-").is_err());
+    }
+
+    #[test]
+    fn writes_control_characters_escaped() {
+        let text = "quote\" backslash\\ \u{1} é";
+        let written = to_string(&Json::String(text.to_string()));
+        assert_eq!(written, "\"quote\\\" backslash\\\\ \\u0001 é\"\n");
+        assert_eq!(string(&written).unwrap(), text);
+    }
+}
@@ -1,47 +1,411 @@
+mod archive;
+mod board;
 mod ctrlc;
+mod date;
+mod item;
+mod ical;
+mod json;
+mod markdown;
+mod org;
+mod recur;
 mod status;
+mod storage;
+mod todotxt;
 mod ui;
+mod version;
 
 use ncurses::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::env;
-use std::fs::File;
-use std::io::{self, BufRead, ErrorKind, Write};
+use std::fs;
+use std::io::ErrorKind;
 use std::process;
+use std::time::{Duration, Instant};
+use date::*;
+use item::*;
 use status::*;
+use storage::{Calendar, Storage};
 use ui::*;
 
-fn list_delete(list: &mut Vec<String>, list_curr: &mut usize) {
+fn list_delete(list: &mut Vec<Item>, list_curr: &mut usize) {
     if *list_curr < list.len() {
-        list.remove(*list_curr);
+        list_remove(list, *list_curr);
         if *list_curr >= list.len() && !list.is_empty() {
             *list_curr = list.len() - 1;
         }
     }
 }
 
-fn load_state(todos: &mut Vec<String>, dones: &mut Vec<String>, file_path: &str) -> io::Result<()> {
-    let file = File::open(file_path)?;
-    for (index, line) in io::BufReader::new(file).lines().enumerate() {
-        match parse_item(&line?) {
-            Some((Status::Todo, title)) => todos.push(title.to_string()),
-            Some((Status::Done, title)) => dones.push(title.to_string()),
-            None => {
-                eprintln!("{}:{}: ERROR: ill-formed item line", file_path, index + 1);
+// Indentation of a panel row, plus a hint of how many subtasks are collapsed under it.
+fn tree_label(list: &[Item], index: usize, collapsed: &HashSet<u64>) -> (String, String) {
+    let indent = "  ".repeat(list[index].depth);
+    let hidden = subtree_end(list, index) - index - 1;
+    if hidden > 0 && collapsed.contains(&list[index].id) {
+        (indent, format!(" ({} hidden)", hidden))
+    } else {
+        (indent, String::new())
+    }
+}
+
+// Text of a panel row: the priority, the title, the due date and the tracked time, if any.
+// Closed items only show their title, the day they were completed and the tracked time.
+fn item_label(list: &[Item], index: usize, status: Status, collapsed: &HashSet<u64>) -> String {
+    let item = &list[index];
+    let (indent, hidden) = tree_label(list, index, collapsed);
+    let mut label = format!("{}- [{}] ", indent, status.mark());
+    let spent = match item.spent(now()) {
+        spent if item.is_timing() => format!(" (timing {})", format_duration(spent)),
+        0 => String::new(),
+        spent => format!(" (spent {})", format_duration(spent)),
+    };
+    if status.is_closed() {
+        label.push_str(&item.title);
+        if let Some(completed) = item.completed {
+            label.push_str(&format!(" ({})", Date::from_timestamp(completed)));
+        }
+        label.push_str(&spent);
+        label.push_str(&hidden);
+        return label;
+    }
+    if let Some(priority) = item.priority {
+        label.push_str(&format!("({}) ", priority));
+    }
+    label.push_str(&item.title);
+    if let Some(due) = item.due() {
+        label.push_str(&format!(" (due {})", due));
+    }
+    if let Some(rule) = item.meta.get(REC_KEY) {
+        label.push_str(&format!(" (repeats {})", rule));
+    }
+    label.push_str(&spent);
+    label.push_str(&hidden);
+    label
+}
+
+// Moves the current item of the `from` list to the `to` list, together with its subtasks
+// if asked to. Closing an item stamps its completion time, stops its timer and leaves the next
+// occurrence of a repeating item in the first column, in its place if it came from there.
+// Reopening clears the time.
+fn move_item(lists: &mut [Vec<Item>], from: Status, to: Status, curr: &mut usize, with_subtasks: bool) {
+    let position = *curr;
+    let (src, dst) = lists_pair(lists, from, to);
+    let depth = src.get(position).map_or(0, |item| item.depth);
+    let moved = if with_subtasks {
+        list_transfer_subtree(dst, src, curr)
+    } else {
+        list_transfer(dst, src, curr)
+    };
+    let completed = to.is_closed().then(now);
+    for item in dst.iter_mut().rev().take(moved) {
+        item.completed = completed;
+        if let Some(completed) = completed {
+            item.stop_timer(completed);
+        }
+    }
+    if !to.is_closed() || from.is_closed() {
+        return;
+    }
+
+    let today = Date::today();
+    let mut id = next_id(lists.iter().flatten());
+    let mut next = Vec::new();
+    let dst = &lists[to.index()];
+    for item in dst[dst.len() - moved..].iter() {
+        if let Some(mut todo) = item.next_occurrence(id, today) {
+            if from == Status::first() {
+                todo.depth += depth;
+            }
+            next.push(todo);
+            id += 1;
+        }
+    }
+    if next.is_empty() {
+        return;
+    }
+    let todos = &mut lists[Status::first().index()];
+    if from == Status::first() {
+        *curr = position;
+        todos.splice(position..position, next);
+        normalize_depths(todos);
+    } else {
+        todos.extend(next);
+    }
+}
+
+// Color pair of a non-highlighted panel row, open items are dimmed while they wait for other
+// items, or colored by their due date, and the others are in the color of their column.
+fn item_pair(item: &Item, status: Status, today: Date, waiting: &HashSet<u64>) -> i16 {
+    match item.due() {
+        _ if status.is_closed() => column_pair(status),
+        _ if waiting.contains(&item.id) => waiting_pair(status),
+        Some(due) if due < today => OVERDUE_PAIR,
+        Some(due) if due == today => DUE_TODAY_PAIR,
+        _ => column_pair(status),
+    }
+}
+
+fn column_pair(status: Status) -> i16 {
+    COLUMN_PAIR + status.index() as i16
+}
+
+// The dimmed pairs of the columns come after the regular ones.
+fn waiting_pair(status: Status) -> i16 {
+    column_pair(status) + Status::count() as i16
+}
+
+// Picks the board of the files before their items are loaded: the columns of their headers,
+// which must be the same in every file that has them, or the ones of the config file.
+fn init_board(files: &[(String, String)]) {
+    let mut picked: Option<(&str, Vec<board::Column>)> = None;
+    for (file_path, text) in files.iter() {
+        let columns = match board::header(text) {
+            Some(Ok(columns)) => columns,
+            Some(Err(reason)) => {
+                eprintln!("ERROR: could not load the columns of `{}`: {}", file_path, reason);
+                process::exit(1);
+            }
+            None => continue,
+        };
+        match &picked {
+            Some((other, picked)) if *picked != columns => {
+                eprintln!("ERROR: `{}` and `{}` define different columns", other, file_path);
                 process::exit(1);
             }
+            Some(_) => {}
+            None => picked = Some((file_path, columns)),
         }
     }
-    Ok(())
+    match picked.map(|(_, columns)| Ok(columns)).unwrap_or_else(board::load_config) {
+        Ok(columns) => board::init(columns),
+        Err(reason) => {
+            eprintln!("ERROR: could not load the columns of the board: {}", reason);
+            process::exit(1);
+        }
+    }
+}
+
+// struct Tab is one of the files given on the command line, with its own lists and layout.
+//      storage : The format of the file, see `storage::detect`.
+//      lists : The items of each column, indexed by `Status::index`.
+//      currs : The highlighted item of each list.
+//      lines : Layout of the file, to write it back the way it was.
+//      errors : Ill-formed lines found while loading the file.
+//      collapsed : Ids of the items whose subtasks are hidden.
+//      read_only : The tab shows an archive, it can be browsed but not changed or saved.
+//      saved : The lists and layout as they were last loaded or saved, see `is_dirty`.
+struct Tab {
+    file_path: String,
+    storage: Box<dyn Storage>,
+    lists: Vec<Vec<Item>>,
+    currs: Vec<usize>,
+    lines: Vec<Line>,
+    errors: Vec<ParseError>,
+    collapsed: HashSet<u64>,
+    read_only: bool,
+    saved: (Vec<Vec<Item>>, Vec<Line>),
 }
 
-fn save_state(todos: &[String], dones: &[String], file_path: &str) {
-    let mut file = File::create(file_path).unwrap();
-    for todo in todos.iter() {
-        writeln!(file, "TODO: {}", todo).unwrap();
+impl Tab {
+    // is_dirty : Whether the file has changes that are not saved yet.
+    fn is_dirty(&self) -> bool {
+        !self.read_only && (self.lists != self.saved.0 || self.lines != self.saved.1)
+    }
+
+    // save : Writes the tab to its file, its changes are no longer dirty then.
+    fn save(&mut self) -> Result<(), String> {
+        if let Err(error) = self.storage.save(&self.file_path, &self.lists, &self.lines) {
+            return Err(format!("could not save state to file `{}`: {}", self.file_path, error));
+        }
+        self.saved = (self.lists.clone(), self.lines.clone());
+        Ok(())
     }
-    for done in dones.iter() {
-        writeln!(file, "DONE: {}", done).unwrap();
+}
+
+// Reads the file into a new tab, `text` is empty for files that don't exist yet.
+fn load_tab(file_path: String, text: &str) -> Result<Tab, String> {
+    // The format is picked by the extension of the file, or guessed from its first lines.
+    let storage = storage::detect(&file_path, text);
+    let mut tab = Tab {
+        file_path,
+        storage,
+        lists: new_lists(),
+        currs: vec![0; Status::count()],
+        lines: Vec::new(),
+        errors: Vec::new(),
+        collapsed: HashSet::new(),
+        read_only: false,
+        saved: (Vec::new(), Vec::new()),
+    };
+    tab.storage.load(text, &mut tab.lists, &mut tab.lines, &mut tab.errors)?;
+    tab.saved = (tab.lists.clone(), tab.lines.clone());
+    Ok(tab)
+}
+
+// Opens the archive of the file of a tab as a read-only tab, see `archive::load`.
+fn load_archive_tab(tab: &Tab) -> Result<Tab, String> {
+    let (lists, lines) = archive::load(&tab.file_path, &tab.lines)?;
+    Ok(Tab {
+        file_path: archive::archive_path(&tab.file_path),
+        storage: Box::new(Format::Todo),
+        saved: (lists.clone(), lines.clone()),
+        lists,
+        currs: vec![0; Status::count()],
+        lines,
+        errors: Vec::new(),
+        collapsed: HashSet::new(),
+        read_only: true,
+    })
+}
+
+// Archives the closed items of a tab and shows the archive again if its tab is open.
+fn archive_tab(tabs: &mut [Tab], tab: usize) -> Result<usize, String> {
+    let Tab { file_path, lists, lines, .. } = &mut tabs[tab];
+    let count = archive::archive(file_path, lists, lines, None)?;
+    let path = archive::archive_path(file_path);
+    if let Some(index) = tabs.iter().position(|tab| tab.read_only && tab.file_path == path) {
+        tabs[index] = load_archive_tab(&tabs[tab])?;
     }
+    Ok(count)
+}
+
+// Moves the closed items of a file completed before the cutoff to its archive
+// without opening the UI.
+fn archive_file(file_path: &str, before: Option<Date>) {
+    let text = match fs::read_to_string(file_path) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("ERROR: could not load state from file `{}`: {}", file_path, error);
+            process::exit(1);
+        }
+    };
+    let files = [(file_path.to_string(), text)];
+    init_board(&files);
+    let mut tab = match load_tab(file_path.to_string(), &files[0].1) {
+        Ok(tab) => tab,
+        Err(reason) => {
+            eprintln!("ERROR: could not load state from file `{}`: {}", file_path, reason);
+            process::exit(1);
+        }
+    };
+    let count = match archive::archive(file_path, &mut tab.lists, &tab.lines, before) {
+        Ok(count) => count,
+        Err(reason) => {
+            eprintln!("ERROR: could not archive the items of `{}`: {}", file_path, reason);
+            process::exit(1);
+        }
+    };
+    if let Err(reason) = tab.save() {
+        eprintln!("ERROR: {}", reason);
+        process::exit(1);
+    }
+    println!("Archived {} item(s) of {} to {}", count, file_path, archive::archive_path(file_path));
+}
+
+// Moves the current item of a column and its subtasks to the same column of another tab.
+// The items get new ids there, the ids of each file are only unique within it, so they only
+// keep waiting for each other.
+fn move_to_tab(tabs: &mut [Tab], from: usize, to: usize, status: Status) -> usize {
+    let mut moved = Vec::new();
+    let source = &mut tabs[from];
+    list_transfer_subtree(&mut moved, &mut source.lists[status.index()], &mut source.currs[status.index()]);
+    forget_prerequisites(&mut source.lists, &moved.iter().map(|item| item.id).collect());
+    let target = &mut tabs[to];
+    let first_id = next_id(target.lists.iter().flatten());
+    let ids: HashMap<u64, u64> = moved.iter().map(|item| item.id).zip(first_id..).collect();
+    for item in moved.iter_mut() {
+        item.id = ids[&item.id];
+        let prerequisites: Vec<u64> = item.prerequisites().iter().filter_map(|id| ids.get(id).copied()).collect();
+        item.set_prerequisites(&prerequisites);
+    }
+    let count = moved.len();
+    target.lists[status.index()].extend(moved);
+    count
+}
+
+// Saves every tab that can be changed, a failed one doesn't stop the others.
+// Returns the index of each failed tab with the reason.
+fn save_tabs(tabs: &mut [Tab]) -> Vec<(usize, String)> {
+    let mut failed = Vec::new();
+    for (index, tab) in tabs.iter_mut().enumerate().filter(|(_, tab)| !tab.read_only) {
+        if let Err(reason) = tab.save() {
+            failed.push((index, reason));
+        }
+    }
+    failed
+}
+
+// Reads `--autosave`, to save after every change, or `--autosave=<seconds>`, to save once
+// no key was pressed for that long. Returns None for other arguments.
+fn parse_autosave(arg: &str) -> Option<Result<Duration, String>> {
+    let rest = arg.strip_prefix("--autosave")?;
+    match rest.strip_prefix('=') {
+        Some(seconds) => Some(
+            seconds
+                .parse()
+                .map(Duration::from_secs)
+                .map_err(|_| format!("`{}` is not a number of seconds", seconds)),
+        ),
+        None if rest.is_empty() => Some(Ok(Duration::ZERO)),
+        None => None,
+    }
+}
+
+// Cycles the filter through every tag used in any list, then back to no filter.
+fn next_filter(lists: &[Vec<Item>], filter: Option<String>) -> Option<String> {
+    let tags: BTreeSet<&String> = lists
+        .iter()
+        .flatten()
+        .flat_map(|item| item.tags.iter())
+        .collect();
+    match filter {
+        Some(current) => tags.into_iter().find(|tag| **tag > current).cloned(),
+        None => tags.into_iter().next().cloned(),
+    }
+}
+
+// Keeps the lines of a file that have a place in the format it is saved in.
+fn convert_lines(source: &dyn Storage, target: &dyn Storage, lines: &mut Vec<Line>) {
+    if source.name() != target.name() {
+        if source.name() == Calendar.name() || target.name() == Calendar.name() {
+            // Calendar components and the lines of text files have no place in each other.
+            lines.clear();
+        } else {
+            // Items are only nested under the headlines of the format they were read from.
+            lines.retain(|line| !matches!(line, Line::Section(_)));
+        }
+    }
+}
+
+// Loads a file and saves its items to another one, in the format of its extension.
+fn convert(from: &str, to: &str) {
+    let mut lines = Vec::<Line>::new();
+    let mut errors = Vec::<ParseError>::new();
+    let text = match fs::read_to_string(from) {
+        Ok(text) => text,
+        Err(error) => {
+            eprintln!("ERROR: could not load state from file `{}`: {}", from, error);
+            process::exit(1);
+        }
+    };
+    let files = [(from.to_string(), text)];
+    init_board(&files);
+    let text = &files[0].1;
+    let mut lists = new_lists();
+    let source = storage::detect(from, text);
+    let target = storage::detect(to, "");
+    if let Err(reason) = source.load(text, &mut lists, &mut lines, &mut errors) {
+        eprintln!("ERROR: could not load state from file `{}`: {}", from, reason);
+        process::exit(1);
+    }
+    for error in errors.iter() {
+        eprintln!("{}:{}: WARNING: {}, kept as it is", from, error.number, error.reason);
+    }
+    convert_lines(source.as_ref(), target.as_ref(), &mut lines);
+    if let Err(error) = target.save(to, &lists, &lines) {
+        eprintln!("ERROR: could not save state to file `{}`: {}", to, error);
+        process::exit(1);
+    }
+    println!("Converted {} ({}) to {} ({})", from, source.name(), to, target.name());
 }
 
 fn main() {
@@ -49,47 +413,126 @@ fn main() {
     ctrlc::init();
 
 
-    // These 2 lines take in the arguments given from terminal
-    let mut args = env::args();
-    args.next().unwrap();
+    // These lines take in the arguments given from terminal. The autosave setting may come
+    // before or after the file paths, without it files are saved on quit.
+    let mut autosave = None;
+    let mut other_args = Vec::new();
+    for arg in env::args().skip(1) {
+        match parse_autosave(&arg) {
+            Some(Ok(interval)) => autosave = Some(interval),
+            Some(Err(reason)) => {
+                eprintln!("Usage: todo-rs --autosave[=<seconds>] <file-path>...");
+                eprintln!("ERROR: {}", reason);
+                process::exit(1);
+            }
+            None => other_args.push(arg),
+        }
+    }
+    let mut args = other_args.into_iter();
+    let first = args.next();
 
-    // This variable 'file_path' is used to get path of given TODO file
-    let file_path = match args.next() {
-        // This code ensures that user inputs the File/Filepath
-        Some(file_path) => file_path,
+    // This variable 'file_paths' is used to get the paths of the given TODO files, one tab each
+    let file_paths: Vec<String> = match first {
+        // `--convert <from> <to>` converts between formats without opening the UI.
+        Some(flag) if flag == "--convert" => match (args.next(), args.next()) {
+            (Some(from), Some(to)) => {
+                convert(&from, &to);
+                return;
+            }
+            _ => {
+                eprintln!("Usage: todo-rs --convert <from> <to>");
+                eprintln!("ERROR: both file paths are needed to convert");
+                process::exit(1);
+            }
+        },
+        // `--archive <file> [YYYY-MM-DD]` archives the items completed before the date, or all of them.
+        Some(flag) if flag == "--archive" => match (args.next(), args.next().map(|date| (Date::parse(&date), date))) {
+            (Some(file_path), None) => {
+                archive_file(&file_path, None);
+                return;
+            }
+            (Some(file_path), Some((Some(before), _))) => {
+                archive_file(&file_path, Some(before));
+                return;
+            }
+            (Some(_), Some((None, date))) => {
+                eprintln!("Usage: todo-rs --archive <file> [YYYY-MM-DD]");
+                eprintln!("ERROR: `{}` is not a date", date);
+                process::exit(1);
+            }
+            (None, _) => {
+                eprintln!("Usage: todo-rs --archive <file> [YYYY-MM-DD]");
+                eprintln!("ERROR: file path is not provided");
+                process::exit(1);
+            }
+        },
+        // This code ensures that user inputs the File/Filepath. Options it doesn't know are
+        // not taken for files, `./--name` opens a file whose name starts with dashes.
+        Some(file_path) => {
+            let file_paths: Vec<String> = std::iter::once(file_path).chain(args).collect();
+            if let Some(option) = file_paths.iter().find(|file_path| file_path.starts_with("--")) {
+                eprintln!("Usage: todo-rs [--autosave[=<seconds>]] <file-path>...");
+                eprintln!("ERROR: unknown option `{}`", option);
+                process::exit(1);
+            }
+            file_paths
+        }
         None => {
-            eprintln!("Usage: todo-rs <file-path>");
+            eprintln!("Usage: todo-rs [--autosave[=<seconds>]] <file-path>...");
+            eprintln!("       todo-rs --convert <from> <to>");
+            eprintln!("       todo-rs --archive <file> [YYYY-MM-DD]");
             eprintln!("ERROR: file path is not provided");
             process::exit(1);
         }
     };
 
-    // Initialize variables to pass in the load_state function, 
-    let mut todos = Vec::<String>::new();
-    let mut todo_curr: usize = 0;
-    let mut dones = Vec::<String>::new();
-    let mut done_curr: usize = 0;
-    
-    let mut notification: String;
-    
-    // call the load_state function and use match to handle errors of file not loaded or not exisiting.
-    match load_state(&mut todos, &mut dones, &file_path) {
-        Ok(()) => notification = format!("Loaded file {}", file_path),
-        Err(error) => {
-            if error.kind() == ErrorKind::NotFound {
-                notification = format!("New file {}", file_path) // not exisiting
-            } else {
-                panic!(
-                    "Could not load state from file `{}`: {:?}", // file not loaded
-                    file_path, error
-                );
+    // Read the files and use match to handle errors of files not loaded or not exisiting.
+    let mut files = Vec::new(); // Path and text of each file
+    let mut loaded = Vec::new(); // What happened to each file, for the notification
+    for file_path in file_paths.iter().cloned() {
+        let text = match fs::read_to_string(&file_path) {
+            Ok(text) => text,
+            Err(error) if error.kind() == ErrorKind::NotFound => {
+                // not exisiting
+                loaded.push(false);
+                files.push((file_path, String::new()));
+                continue;
             }
-        }
-    };
+            Err(error) => {
+                // file not loaded
+                eprintln!("ERROR: could not load state from file `{}`: {}", file_path, error);
+                process::exit(1);
+            }
+        };
+        loaded.push(true);
+        files.push((file_path, text));
+    }
+    // The columns come from the headers of the files or the config file.
+    init_board(&files);
+    let mut tabs: Vec<Tab> = files
+        .into_iter()
+        .map(|(file_path, text)| match load_tab(file_path.clone(), &text) {
+            Ok(tab) => tab,
+            Err(reason) => {
+                eprintln!("ERROR: could not load state from file `{}`: {}", file_path, reason);
+                process::exit(1);
+            }
+        })
+        .collect();
+    let mut notification = tabs
+        .iter()
+        .zip(loaded)
+        .map(|(tab, exists)| {
+            let what = if exists { "Loaded file" } else { "New file" };
+            format!("{} {} ({})", what, tab.file_path, tab.storage.name())
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
 
     // This code sets up the terminal for ncurses based UI 
     // by configuring input behavior, cursor visibility, and color pairs.
     initscr();
+    set_paste_mode(true);
     noecho();
     keypad(stdscr(), true);
     timeout(16); // for running in 60 FPS
@@ -98,13 +541,34 @@ fn main() {
     init_color(0, 0, 43 * 4, 54 * 4);
     init_pair(REGULAR_PAIR, COLOR_WHITE, COLOR_GREEN);
     init_pair(HIGHLIGHT_PAIR, COLOR_GREEN, COLOR_WHITE);
+    init_pair(OVERDUE_PAIR, COLOR_WHITE, COLOR_RED);
+    init_pair(DUE_TODAY_PAIR, COLOR_BLACK, COLOR_YELLOW);
+    for status in Status::all() {
+        let foreground = if status.color() == COLOR_WHITE || status.color() == COLOR_YELLOW {
+            COLOR_BLACK
+        } else {
+            COLOR_WHITE
+        };
+        init_pair(column_pair(status), foreground, status.color());
+        let dimmed = if foreground == COLOR_BLACK { COLOR_BLUE } else { COLOR_BLACK };
+        init_pair(waiting_pair(status), dimmed, status.color());
+    }
 
     // These variables and the Ui instance are essential components 
     // for managing the state and behavior of the App.
     let mut quit = false; // Flag for quiting the app
-    let mut panel = Status::Todo; // To know which list is active, TODO or DONE
+    let mut tab = 0; // To know which file is shown
+    let mut panel = Status::first(); // To know which list is active
     let mut editing = false; // To know when user is typing/editing
     let mut editing_cursor = 0; // Makes the cursor invisible inside the app
+    let mut by_priority = false; // Show the open panels ordered by priority instead of the manual order
+    let mut filter: Option<String> = None; // Only show items with this +project or @context tag
+    let mut confirm_move: Option<Status> = None; // Waiting for y/n on moving the subtasks of the current item there
+    let mut confirm_done: Option<u64> = None; // Item refused as done because it waits for others, until the next key
+    let mut linking: Option<(usize, u64)> = None; // Tab and id of the item picked with w to wait for another one
+    let mut save_as: Option<String> = None; // Path typed after S to save the current tab somewhere else
+    let mut save_as_cursor = 0;
+    let mut autosave_at: Option<Instant> = None; // When the changes are saved, with autosave on
     let mut ui = Ui::default();
 
     // main loop of the terminal App. 
@@ -112,177 +576,504 @@ fn main() {
     // and no Ctrl+C signal (interrupt signal) has been received.
     while !quit && !ctrlc::poll() {
         erase(); // Clear terminal
+        let timer_label = tabs
+            .iter()
+            .flat_map(|tab| tab.lists.iter().flatten())
+            .find(|item| item.is_timing())
+            .map(|item| format!("[{} {}] ", format_duration(item.spent(now())), item.title));
+        let tab_count = tabs.len();
+        let tab_paths: Vec<String> = tabs.iter().map(|tab| tab.file_path.clone()).collect();
+        let dirty: Vec<bool> = tabs.iter().map(Tab::is_dirty).collect();
+        let Tab { file_path, storage, lists, currs, lines, errors, collapsed, read_only, saved } = &mut tabs[tab];
+
+        // Keys that would change the items do nothing in a read-only tab.
+        if *read_only && ui.key.is_some_and(|key| "\n][bcKJ+-idsr><mMAtwS".contains(key as u8 as char)) {
+            ui.key = None;
+            notification.push_str("The archive is read-only.");
+        }
 
         let mut x = 0;
         let mut y = 0;
         getmaxyx(stdscr(), &mut y, &mut x); // Gets the max co-ordinates of the terminal, i.e. window size
+        let width = x / (Status::count() as i32 + 1); // The panel of each column and NOTES share the screen
+        let today = Date::today(); // Used to color overdue and due-today items
+        // Open items that wait for other open items, they are dimmed.
+        let waiting: HashSet<u64> = Status::all()
+            .filter(|status| !status.is_closed())
+            .flat_map(|status| lists[status.index()].iter())
+            .filter(|item| !open_prerequisites(lists, item).is_empty())
+            .map(|item| item.id)
+            .collect();
+
+        // Order in which the items of each panel are displayed and navigated.
+        let views: Vec<Vec<usize>> = Status::all()
+            .map(|status| {
+                let list = &lists[status.index()];
+                let mut view = tree_view(list, collapsed);
+                if by_priority && !status.is_closed() {
+                    // Items with a priority first, (A) before (B).
+                    view_sort_by_key(list, &mut view, |item| (item.priority.is_none(), item.priority));
+                }
+                if let Some(tag) = &filter {
+                    // The row being edited stays visible even if its title no longer has the tag.
+                    let edited = |index| editing && status == panel && index == currs[status.index()];
+                    view.retain(|&index| list[index].has_tag(tag) || edited(index));
+                }
+                view
+            })
+            .collect();
+        for status in Status::all() {
+            view_snap(&views[status.index()], &mut currs[status.index()]);
+        }
+        // The header row marks a file with unsaved changes with [+], next to the active filter.
+        let dirty_label = if dirty[tab] { "[+] " } else { "" };
+        let header_label = match &filter {
+            Some(tag) => format!("{}Filter: {}", dirty_label, tag),
+            None => dirty_label.to_string(),
+        };
 
         // A new `ui` is initialized, at location (0,0), with Layout kind Vertical,
         ui.begin(Vec2::new(0, 0), LayoutKind::Vert);
         {
-            // Then 2 labels are initialized with variable notification and other with an empty string.
-            ui.label_fixed_width(&notification, x, REGULAR_PAIR);
-            ui.label_fixed_width("", x, REGULAR_PAIR);
-
-            // Initialize a Vertical type of layout in the UI for the TODO list.
-            ui.begin_layout(LayoutKind::Horz);
-            {
-                // If the status of the panel is TODO, the code is in action
-                ui.begin_layout(LayoutKind::Vert);
-                {
-                    if panel == Status::Todo {
-                        ui.label_fixed_width("TODO", x / 2, HIGHLIGHT_PAIR);
-                        for (index, todo) in todos.iter_mut().enumerate() {
-                            if index == todo_curr {
-                                if editing {
-                                    ui.edit_field(todo, &mut editing_cursor, x / 2);
-
-                                    if let Some('\n') = ui.key.take().map(|x| x as u8 as char) {
-                                        editing = false;
-                                    }
-                                } else {
-                                    ui.label_fixed_width(
-                                        &format!("- [ ] {}", todo),
-                                        x / 2,
-                                        HIGHLIGHT_PAIR,
-                                    );
-                                    if let Some('r') = ui.key.map(|x| x as u8 as char) {
-                                        editing = true;
-                                        editing_cursor = todo.len();
-                                        ui.key = None;
-                                    }
-                                }
-                            } else {
-                                ui.label_fixed_width(
-                                    &format!("- [ ] {}", todo),
-                                    x / 2,
-                                    REGULAR_PAIR,
-                                );
-                            }
+            // Then 2 labels are initialized with variable notification and other with the active filter.
+            // The running timer, if any, stays in front of the notification.
+            // While a path is typed after S, it takes the place of the notification.
+            if let Some(path) = &mut save_as {
+                let prompt = format!("Save {} as: ", file_path);
+                ui.begin_layout(LayoutKind::Horz);
+                ui.label(&prompt, REGULAR_PAIR);
+                ui.edit_field(path, &mut save_as_cursor, x - prompt.len() as i32);
+                ui.end_layout();
+                match ui.key.take() {
+                    // The file is written in the format its extension names. Other extensions keep
+                    // the current format, so does a `.txt` that would be read back in the default one.
+                    Some(KEY_ENTER | 10) => {
+                        let target = storage::from_extension(path, &storage.format(lists, lines))
+                            .filter(|target| target.name() != storage.name());
+                        let mut target_lines = lines.clone();
+                        if let Some(target) = &target {
+                            convert_lines(storage.as_ref(), target.as_ref(), &mut target_lines);
                         }
-                        
-                        // This code is for monitoring the input from keyboard.
-                        if let Some(key) = ui.key.take() {
-                            match key as u8 as char {
-                                'K' => list_drag_up(&mut todos, &mut todo_curr),
-                                'J' => list_drag_down(&mut todos, &mut todo_curr),
-                                'i' => {
-                                    todos.insert(todo_curr, String::new());
-                                    editing_cursor = 0;
-                                    editing = true;
-                                    notification.push_str("What needs to be done?");
-                                }
-                                'd' => {
-                                    notification.push_str(
-                                        "Can't remove items from TODO. Mark it as DONE first.",
-                                    );
-                                }
-                                'k' => list_up(&mut todo_curr),
-                                'j' => list_down(&todos, &mut todo_curr),
-                                'g' => list_first(&mut todo_curr),
-                                'G' => list_last(&todos, &mut todo_curr),
-                                '\n' => {
-                                    list_transfer(&mut dones, &mut todos, &mut todo_curr);
-                                    notification.push_str("DONE!")
-                                }
-                                '\t' => {
-                                    panel = panel.toggle();
-                                }
-                                _ => {
-                                    ui.key = Some(key);
+                        match target.as_ref().unwrap_or(storage).save(path, lists, &target_lines) {
+                            Ok(()) => {
+                                notification = format!("Saved state to {}", path);
+                                *file_path = save_as.take().unwrap_or_default();
+                                if let Some(target) = target {
+                                    *storage = target;
                                 }
+                                *lines = target_lines;
+                                *saved = (lists.clone(), lines.clone());
+                            }
+                            Err(error) => {
+                                notification = format!("Could not save state to file `{}`: {}", path, error);
+                                save_as = None;
                             }
                         }
-                    } else {
-                        ui.label_fixed_width("TODO", x / 2, REGULAR_PAIR);
-                        for todo in todos.iter() {
-                            ui.label_fixed_width(&format!("- [ ] {}", todo), x / 2, REGULAR_PAIR);
+                    }
+                    Some(27) => save_as = None,
+                    _ => {}
+                }
+            } else {
+                match &timer_label {
+                    Some(timer) => ui.label_fixed_width(&format!("{}{}", timer, notification), x, REGULAR_PAIR),
+                    None => ui.label_fixed_width(&notification, x, REGULAR_PAIR),
+                }
+            }
+            ui.label_fixed_width(&header_label, x, REGULAR_PAIR);
+
+            // One tab per file when several are open, the shown one highlighted.
+            if tab_count > 1 {
+                ui.begin_layout(LayoutKind::Horz);
+                for (index, file_path) in tab_paths.iter().enumerate() {
+                    let pair = if index == tab { HIGHLIGHT_PAIR } else { REGULAR_PAIR };
+                    let marker = if dirty[index] { " [+]" } else { "" };
+                    ui.label(&format!(" {}{} ", file_path, marker), pair);
+                }
+                ui.end_layout();
+            }
+
+            // Ill-formed lines of the file, until the user decides what to do with them.
+            if !errors.is_empty() {
+                ui.label_fixed_width(
+                    &format!(
+                        "{} ill-formed line(s) in {}. v: keep them as they are, x: drop them",
+                        errors.len(),
+                        file_path
+                    ),
+                    x,
+                    HIGHLIGHT_PAIR,
+                );
+                for error in errors.iter() {
+                    let text = match &lines[error.line] {
+                        Line::Raw(text) => text.as_str(),
+                        Line::Item(_) | Line::Section(_) => "",
+                    };
+                    ui.label_fixed_width(
+                        &format!("{}:{}: {}: {}", file_path, error.number, error.reason, text),
+                        x,
+                        REGULAR_PAIR,
+                    );
+                }
+                ui.label_fixed_width("", x, REGULAR_PAIR);
+
+                match ui.key.map(|x| x as u8 as char) {
+                    Some('v') if !editing => {
+                        errors.clear();
+                        notification.push_str("Ill-formed lines will be saved as they are.");
+                        ui.key = None;
+                    }
+                    Some('x') if !editing => {
+                        for error in errors.drain(..).rev() {
+                            lines.remove(error.line);
                         }
+                        notification.push_str("Ill-formed lines will be dropped on save.");
+                        ui.key = None;
                     }
+                    _ => {}
                 }
-                ui.end_layout(); // This closes the UI for TODO status.
+            }
 
+            // Initialize a Horizontal type of layout in the UI for the panels.
+            ui.begin_layout(LayoutKind::Horz);
+            {
+                // One Vertical layout per column, the active panel handles the keys.
+                for status in Status::all() {
+                    let list = status.index();
+                    let view = &views[list];
+                    let total: u64 = lists[list].iter().map(|item| item.spent(now())).sum();
+                    let header = if by_priority && !status.is_closed() {
+                        format!("{} (by priority)", status.name())
+                    } else if status.is_closed() && total > 0 {
+                        format!("{} (total {})", status.name(), format_duration(total))
+                    } else {
+                        status.name().to_string()
+                    };
+                    ui.begin_layout(LayoutKind::Vert);
+                    if status != panel {
+                        ui.label_fixed_width(&header, width, column_pair(status));
+                        for &index in view.iter() {
+                            let label = item_label(&lists[list], index, status, collapsed);
+                            let pair = item_pair(&lists[list][index], status, today, &waiting);
+                            ui.label_fixed_width(&label, width, pair);
+                        }
+                        ui.end_layout();
+                        continue;
+                    }
 
-                // Initialize a Vertical type of layout in the UI for the DONE list. 
-                ui.begin_layout(LayoutKind::Vert);
-                {
-                    // Enters here if the Status of panel is DONE.
-                    if panel == Status::Done {
-                        ui.label_fixed_width("DONE", x / 2, HIGHLIGHT_PAIR);
-                        for (index, done) in dones.iter_mut().enumerate() {
-                            if index == done_curr {
-                                if editing {
-                                    ui.edit_field(done, &mut editing_cursor, x / 2);
-
-                                    if let Some('\n') = ui.key.take().map(|x| x as u8 as char) {
-                                        editing = false;
-                                    }
-                                } else {
-                                    ui.label_fixed_width(
-                                        &format!("- [x] {}", done),
-                                        x / 2,
-                                        HIGHLIGHT_PAIR,
-                                    );
-                                    if let Some('r') = ui.key.map(|x| x as u8 as char) {
-                                        editing = true;
-                                        editing_cursor = done.len();
-                                        ui.key = None;
-                                    }
+                    ui.label_fixed_width(&header, width, HIGHLIGHT_PAIR);
+                    for &index in view.iter() {
+                        let label = item_label(&lists[list], index, status, collapsed);
+                        let item = &mut lists[list][index];
+                        if index == currs[list] {
+                            if editing {
+                                ui.edit_field(&mut item.title, &mut editing_cursor, width);
+
+                                if let Some('\n') = ui.key.take().map(|x| x as u8 as char) {
+                                    item.retitle();
+                                    editing = false;
                                 }
                             } else {
-                                ui.label_fixed_width(
-                                    &format!("- [x] {}", done),
-                                    x / 2,
-                                    REGULAR_PAIR,
-                                );
+                                ui.label_fixed_width(&label, width, HIGHLIGHT_PAIR);
+                                if let Some('r') = ui.key.map(|x| x as u8 as char) {
+                                    editing = true;
+                                    editing_cursor = item.title.len();
+                                    ui.key = None;
+                                }
                             }
+                        } else {
+                            ui.label_fixed_width(&label, width, item_pair(item, status, today, &waiting));
                         }
+                    }
 
-                        // This code is for monitoring the input from keyboard.
-                        if let Some(key) = ui.key.take() {
-                            match key as u8 as char {
-                                'K' => list_drag_up(&mut dones, &mut done_curr),
-                                'J' => list_drag_down(&mut dones, &mut done_curr),
-                                'k' => list_up(&mut done_curr),
-                                'j' => list_down(&dones, &mut done_curr),
-                                'g' => list_first(&mut done_curr),
-                                'G' => list_last(&dones, &mut done_curr),
-                                'i' => {
-                                    notification.push_str(
-                                        "Can't insert new DONE items. Only TODO is allowed.",
-                                    );
-                                }
-                                'd' => {
-                                    list_delete(&mut dones, &mut done_curr);
-                                    notification.push_str("Into The Abyss!");
+                    // This code is for monitoring the input from keyboard.
+                    // Enter advances the item through the workflow, ] and [ move it to the next
+                    // and previous column, b blocks it and c cancels it on boards that have those columns.
+                    // An item that waits for open items is only marked as done if asked twice.
+                    let key = ui.key.take();
+                    let blocked = Status::find("BLOCKED").filter(|blocked| *blocked != status);
+                    let cancelled = Status::find("CANCELLED");
+                    let target = match key.map(|x| x as u8 as char) {
+                        Some('\n') => Some(status.advance()),
+                        Some(']') => status.next(),
+                        Some('[') => status.prev(),
+                        Some('b') if !status.is_closed() => blocked,
+                        Some('c') if !status.is_closed() => cancelled,
+                        _ => None,
+                    }
+                    .filter(|to| *to != status);
+                    let current = lists[list].get(currs[list]).filter(|_| !view.is_empty());
+                    let current_id = current.map(|item| item.id);
+                    let forced = key.is_some() && confirm_done.take().is_some_and(|id| current_id == Some(id));
+                    let prerequisites: Vec<String> = match (target, current) {
+                        (Some(to), Some(item)) if to == Status::first_closed() && !forced => {
+                            open_prerequisites(lists, item).iter().map(|item| item.title.clone()).collect()
+                        }
+                        _ => Vec::new(),
+                    };
+                    let curr = &mut currs[list];
+                    match (key, confirm_move.take(), target) {
+                        (None, pending, _) => confirm_move = pending,
+                        (Some(KEY_BTAB), _, _) => panel = panel.cycle_back(),
+                        (Some(key), Some(to), _) => match key as u8 as char {
+                            'y' => {
+                                move_item(lists, status, to, curr, true);
+                                notification.push_str(&format!("{}, subtasks included!", to.name()));
+                            }
+                            'n' => {
+                                move_item(lists, status, to, curr, false);
+                                notification.push_str(&format!("{}! Subtasks stay in {}.", to.name(), status.name()));
+                            }
+                            _ => notification.push_str("Nothing changed."),
+                        },
+                        (Some(_), None, Some(to)) if !prerequisites.is_empty() => {
+                            confirm_done = current_id;
+                            notification.push_str(&format!(
+                                "Still waiting for {}. Press again to mark it as {} anyway.",
+                                prerequisites.join(", "),
+                                to.name()
+                            ));
+                        }
+                        (Some(_), None, Some(to)) if !view.is_empty() => {
+                            if subtree_end(&lists[list], *curr) > *curr + 1 {
+                                confirm_move = Some(to);
+                                notification.push_str(&format!("Move its subtasks to {} too? [y/n]", to.name()));
+                            } else {
+                                move_item(lists, status, to, curr, false);
+                                if status == Status::first_closed() && to == Status::first() {
+                                    notification.push_str("No, not done yet...");
+                                } else {
+                                    notification.push_str(&format!("{}!", to.name()));
                                 }
-                                '\n' => {
-                                    list_transfer(&mut todos, &mut dones, &mut done_curr);
-                                    notification.push_str("No, not done yet...")
+                            }
+                        }
+                        (Some(key), None, _) => match key as u8 as char {
+                            'K' | 'J' if by_priority && !status.is_closed() => {
+                                notification.push_str(
+                                    "Can't drag items while sorted by priority. Press p to go back.",
+                                );
+                            }
+                            'K' | 'J' if filter.is_some() => {
+                                notification.push_str("Can't drag items while filtered. Press f to cycle back.");
+                            }
+                            'K' => list_drag_up(&mut lists[list], curr),
+                            'J' => list_drag_down(&mut lists[list], curr),
+                            '+' if !status.is_closed() && !view.is_empty() => {
+                                if let Some(item) = lists[list].get_mut(*curr) {
+                                    item.raise_priority();
                                 }
-                                '\t' => {
-                                    panel = panel.toggle();
+                            }
+                            '-' if !status.is_closed() && !view.is_empty() => {
+                                if let Some(item) = lists[list].get_mut(*curr) {
+                                    item.lower_priority();
                                 }
-                                _ => ui.key = Some(key),
                             }
-                        }
-                    } else {
-                        ui.label_fixed_width("DONE", x / 2, REGULAR_PAIR);
-                        for done in dones.iter() {
-                            ui.label_fixed_width(&format!("- [x] {}", done), x / 2, REGULAR_PAIR);
-                        }
+                            'p' if !status.is_closed() => {
+                                by_priority = !by_priority;
+                            }
+                            'i' if status.is_closed() => {
+                                notification.push_str(&format!(
+                                    "Can't insert new {} items. Only open ones are allowed.",
+                                    status.name()
+                                ));
+                            }
+                            'i' => {
+                                let id = next_id(lists.iter().flatten());
+                                let mut item = Item::new(id, "");
+                                // The new item becomes a sibling of the current one.
+                                item.depth = lists[list].get(*curr).map_or(0, |curr| curr.depth);
+                                lists[list].insert(*curr, item);
+                                editing_cursor = 0;
+                                editing = true;
+                                notification.push_str("What needs to be done?");
+                            }
+                            'd' if !status.is_closed() => {
+                                notification.push_str(&format!(
+                                    "Can't remove open items. Mark it as {} first.",
+                                    Status::first_closed().name()
+                                ));
+                            }
+                            'd' if !view.is_empty() => {
+                                let deleted = HashSet::from([lists[list][*curr].id]);
+                                list_delete(&mut lists[list], curr);
+                                forget_prerequisites(lists, &deleted);
+                                notification.push_str("Into The Abyss!");
+                            }
+                            'k' => view_navigate(view, curr, |_, curr| list_up(curr)),
+                            'j' => view_navigate(view, curr, list_down),
+                            'g' => view_navigate(view, curr, |_, curr| list_first(curr)),
+                            'G' => view_navigate(view, curr, list_last),
+                            's' if !status.is_closed() => {
+                                // Items without a due date go to the bottom.
+                                list_sort_by_key(&mut lists[list], curr, |item| (item.due().is_none(), item.due()));
+                                notification.push_str("Sorted by due date");
+                            }
+                            '>' => list_indent(&mut lists[list], *curr),
+                            '<' => list_outdent(&mut lists[list], *curr),
+                            'h' => list_collapse(&lists[list], curr, collapsed),
+                            'l' => list_expand(&lists[list], *curr, collapsed),
+                            '\t' => {
+                                panel = panel.cycle();
+                            }
+                            _ => {
+                                ui.key = Some(key);
+                            }
+                        },
+                    }
+                    ui.end_layout();
+                }
+
+                // Initialize a Vertical type of layout in the UI for the notes of the highlighted item.
+                ui.begin_layout(LayoutKind::Vert);
+                {
+                    ui.label_fixed_width("NOTES", width, REGULAR_PAIR);
+                    let highlighted = lists[panel.index()].get(currs[panel.index()]);
+                    for item in highlighted.iter().flat_map(|item| open_prerequisites(lists, item)) {
+                        ui.label_fixed_width(&format!("Waits for: {}", item.title), width, REGULAR_PAIR);
+                    }
+                    for note in highlighted.iter().flat_map(|item| item.notes.iter()) {
+                        ui.label_fixed_width(note, width, REGULAR_PAIR);
                     }
                 }
-                ui.end_layout(); // This closes the UI for DONE status.
+                ui.end_layout(); // This closes the UI for notes.
             }
             ui.end_layout(); // This closes the UI for notifications
 
         }
         ui.end(); // This finally closes the whole UI
 
-        // If the q key is pressed, the quit flag is set and thus the program terminates, as while loop ends
-        if let Some('q') = ui.key.take().map(|x| x as u8 as char) {
-            quit = true;
+        // A paste only goes into the title being edited.
+        if ui.paste.take().is_some() {
+            notification.push_str("Press r to rename or i to insert an item, then paste.");
+        }
+
+        // Keys that work the same in all panels. } and { switch to the next and previous tab,
+        // m and M move the current item to the next and previous tab.
+        let next_tab = (tab + 1) % tab_count;
+        let prev_tab = (tab + tab_count - 1) % tab_count;
+        match ui.key.take().map(|x| x as u8 as char) {
+            // If the q key is pressed, every file is saved and the quit flag is set, thus the program
+            // terminates as the while loop ends. A failed save keeps the program running to retry.
+            Some('q') => {
+                let failed = save_tabs(&mut tabs);
+                match failed.first() {
+                    None => quit = true,
+                    Some(&(index, _)) => {
+                        tab = index;
+                        let reasons: Vec<&str> = failed.iter().map(|(_, reason)| reason.as_str()).collect();
+                        notification = format!(
+                            "ERROR: {}. Press q to retry or S to save elsewhere.",
+                            reasons.join(", ")
+                        );
+                    }
+                }
+            }
+            Some('S') => {
+                save_as_cursor = tabs[tab].file_path.len();
+                save_as = Some(tabs[tab].file_path.clone());
+            }
+            Some('f') => filter = next_filter(&tabs[tab].lists, filter.take()),
+            // t starts the timer of the current item and stops any other, or stops its own.
+            Some('t') if panel.is_closed() => {
+                notification.push_str("Can't track time on closed items.");
+            }
+            Some('t') if !views[panel.index()].is_empty() => {
+                let time = now();
+                let curr = tabs[tab].currs[panel.index()];
+                let was_timing = tabs[tab].lists[panel.index()][curr].is_timing();
+                for item in tabs.iter_mut().flat_map(|tab| tab.lists.iter_mut().flatten()) {
+                    item.stop_timer(time);
+                }
+                let item = &mut tabs[tab].lists[panel.index()][curr];
+                if was_timing {
+                    notification.push_str(&format!("Timer stopped at {}.", format_duration(item.spent(time))));
+                } else {
+                    item.start_timer(time);
+                    notification.push_str("Timer started.");
+                }
+            }
+            // w picks the current item, a second w on another item makes the picked one
+            // wait for it, or stop waiting for it.
+            Some('w') if !views[panel.index()].is_empty() => {
+                let Tab { lists, currs, .. } = &mut tabs[tab];
+                let highlighted = &lists[panel.index()][currs[panel.index()]];
+                let (id, title) = (highlighted.id, highlighted.title.clone());
+                match linking.take() {
+                    None => {
+                        linking = Some((tab, id));
+                        notification.push_str("Highlight the item it waits for and press w again.");
+                    }
+                    Some((other, _)) if other != tab => {
+                        notification.push_str("Items can only wait for items of their own file.");
+                    }
+                    Some((_, dependent)) if dependent == id => notification.push_str("Nothing changed."),
+                    Some((_, dependent)) if waits_for(lists, id, dependent) => {
+                        notification.push_str(&format!("Can't wait for {}, it waits for this item.", title));
+                    }
+                    Some((_, dependent)) => match lists.iter_mut().flatten().find(|item| item.id == dependent) {
+                        Some(item) => {
+                            let mut prerequisites = item.prerequisites();
+                            if prerequisites.contains(&id) {
+                                prerequisites.retain(|&other| other != id);
+                                notification.push_str(&format!("{} no longer waits for {}.", item.title, title));
+                            } else {
+                                prerequisites.push(id);
+                                notification.push_str(&format!("{} waits for {}!", item.title, title));
+                            }
+                            item.set_prerequisites(&prerequisites);
+                        }
+                        None => notification.push_str("Nothing changed."),
+                    },
+                }
+            }
+            Some('}') => tab = next_tab,
+            Some('{') => tab = prev_tab,
+            Some('m' | 'M') if tab_count == 1 => {
+                notification.push_str("Open more files to move items between them.");
+            }
+            Some(key @ ('m' | 'M')) if !views[panel.index()].is_empty() => {
+                let to = if key == 'm' { next_tab } else { prev_tab };
+                if tabs[to].read_only {
+                    notification.push_str("Can't move items into the archive. Press A to archive them.");
+                } else if move_to_tab(&mut tabs, tab, to, panel) > 0 {
+                    notification.push_str(&format!("Moved to {}!", tabs[to].file_path));
+                }
+            }
+            // a shows the archive of the file in a read-only tab, A archives its closed items.
+            Some('a') if tabs[tab].read_only => {}
+            Some('a') => {
+                let path = archive::archive_path(&tabs[tab].file_path);
+                match tabs.iter().position(|other| other.read_only && other.file_path == path) {
+                    Some(index) => tab = index,
+                    None => match load_archive_tab(&tabs[tab]) {
+                        Ok(archive) => {
+                            tabs.push(archive);
+                            tab = tabs.len() - 1;
+                        }
+                        Err(reason) => notification.push_str(&format!("Can't open the archive: {}", reason)),
+                    },
+                }
+            }
+            Some('A') => match archive_tab(&mut tabs, tab) {
+                Ok(0) => notification.push_str("Nothing to archive."),
+                Ok(count) => notification.push_str(&format!(
+                    "Archived {} item(s) to {}!",
+                    count,
+                    archive::archive_path(&tabs[tab].file_path)
+                )),
+                Err(reason) => notification.push_str(&format!("Can't archive: {}", reason)),
+            },
+            _ => {}
+        }
+
+        // With autosave on, the changed files are saved once the interval since the last key
+        // has passed and no title or path is being typed. A failed save waits for the next key.
+        if autosave_at.is_some_and(|at| Instant::now() >= at) && !editing && save_as.is_none() {
+            autosave_at = None;
+            for tab in tabs.iter_mut().filter(|tab| tab.is_dirty()) {
+                if let Err(reason) = tab.save() {
+                    notification = format!("ERROR: {}. Press S to save elsewhere.", reason);
+                }
+            }
         }
 
         refresh(); // refresh() is used to refresh the screen.
@@ -292,12 +1083,26 @@ fn main() {
         let key: i32 = getch();
         if key != ERR {
             notification.clear()    ;
-            ui.key = Some(key);
+            autosave_at = autosave.map(|interval| Instant::now() + interval);
+            match read_paste(key) {
+                Some(text) => ui.paste = Some(text),
+                None => ui.key = Some(key),
+            }
         }
     } // The while loop ends here
 
+    set_paste_mode(false);
     endwin(); // For closing the UI window
 
-    save_state(&todos, &dones, &file_path); // All the changes are save to given file
-    println!("Saved state to {}", file_path);
+    // q saved the files already. After Ctrl+C they are saved now, with no way left to retry.
+    let failed = if quit { Vec::new() } else { save_tabs(&mut tabs) };
+    for (index, tab) in tabs.iter().enumerate().filter(|(_, tab)| !tab.read_only) {
+        match failed.iter().find(|(failed, _)| *failed == index) {
+            None => println!("Saved state to {}", tab.file_path),
+            Some((_, reason)) => eprintln!("ERROR: {}", reason),
+        }
+    }
+    if !failed.is_empty() {
+        process::exit(1);
+    }
 }
@@ -0,0 +1,125 @@
+use crate::item::Item;
+use crate::status::{format_lines, parse_item_text, Status};
+
+// Bullets that can start a task list item, `-` is used when writing.
+const BULLETS: [&str; 3] = ["- [", "* [", "+ ["];
+
+// Columns a tab indents to the next multiple of, like in CommonMark.
+const TAB_WIDTH: usize = 4;
+
+// Reads a GitHub task list item, `- [ ] title` or `- [x] title`. GitHub only knows these two,
+// the other states use the checkboxes of common task list extensions, see `Status::mark`.
+// Other bullets, headings and paragraphs are not items.
+// The depth of the item is the width of its indentation in columns, `nest` turns it into a level.
+pub fn parse_item(line: &str) -> Option<Result<(Status, Item), String>> {
+    let text = line.trim_start_matches([' ', '\t']);
+    let indent = line[..line.len() - text.len()].chars().fold(0, |width, c| {
+        if c == '\t' {
+            width + TAB_WIDTH - width % TAB_WIDTH
+        } else {
+            width + 1
+        }
+    });
+    let text = BULLETS
+        .iter()
+        .find_map(|bullet| text.strip_prefix(bullet))?;
+    let mut chars = text.chars();
+    let mark = chars.next()?.to_ascii_lowercase();
+    let text = chars.as_str().strip_prefix(']')?;
+    let status = Status::all().find(|status| status.mark() == mark)?;
+    Some(parse_item_text(status, 0, text).map(|(status, item)| {
+        (
+            status,
+            Item {
+                depth: indent,
+                ..item
+            },
+        )
+    }))
+}
+
+// nest : Turns the indentation width of an item into its depth. Any indentation deeper than
+// the item above nests the item one level under it, like items indented by 3 spaces under
+// a `1.` or by tabs. `indents` keeps the widths of the items the next one may be nested under.
+pub fn nest(indents: &mut Vec<usize>, indent: usize) -> usize {
+    while indents.last().is_some_and(|&last| last >= indent) {
+        indents.pop();
+    }
+    indents.push(indent);
+    indents.len() - 1
+}
+
+// The id and timestamps are left out to keep the file readable, items get fresh ids every time
+// the file is loaded. Only the items other items wait for keep theirs, as an `id:` tag.
+pub fn format_item(status: &Status, item: &Item) -> String {
+    format_lines(
+        item,
+        &format!("- [{}] {}", status.mark(), item.format_plain()),
+        "",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::{new_lists, Format, Line};
+    use crate::storage::Storage;
+
+    #[test]
+    fn reads_task_list_items() {
+        let (status, item) = parse_item("* [X] Ship it due:2026-10-20").unwrap().unwrap();
+        assert_eq!(status, Status::first_closed());
+        assert_eq!(item.title, "Ship it");
+        assert_eq!(parse_item("\t- [ ] tab").unwrap().unwrap().1.depth, 4);
+        assert_eq!(parse_item("  \t- [ ] tab").unwrap().unwrap().1.depth, 4);
+        assert_eq!(parse_item("   - [ ] three").unwrap().unwrap().1.depth, 3);
+        assert!(parse_item("- [ ]no space").unwrap().is_err());
+        assert!(parse_item("- not a task").is_none());
+        assert!(parse_item("1. [ ] ordered").is_none());
+    }
+
+    #[test]
+    fn deeper_indentation_nests_one_level() {
+        let mut indents = Vec::new();
+        let depths: Vec<usize> = [0, 3, 3, 7, 3, 0, 4, 2]
+            .iter()
+            .map(|&indent| nest(&mut indents, indent))
+            .collect();
+        assert_eq!(depths, [0, 1, 1, 2, 1, 0, 1, 1]);
+    }
+
+    #[test]
+    fn reads_lists_indented_by_three_spaces_and_tabs() {
+        let text = "1. Release\n   - [ ] Tag it\n      - [x] Bump the version\n   - [ ] Announce\n\
+                    - [ ] Other\n\t- [ ] Tabbed\n\t\t- [ ] Deeper\n";
+        let mut lists = new_lists();
+        let mut lines = Vec::new();
+        let mut errors = Vec::new();
+        Format::Markdown
+            .load(text, &mut lists, &mut lines, &mut errors)
+            .unwrap();
+        assert!(errors.is_empty());
+        let depths = |status: Status| -> Vec<(String, usize)> {
+            lists[status.index()]
+                .iter()
+                .map(|item| (item.title.clone(), item.depth))
+                .collect()
+        };
+        let todo = [
+            ("Tag it", 0),
+            ("Announce", 0),
+            ("Other", 0),
+            ("Tabbed", 1),
+            ("Deeper", 2),
+        ];
+        assert_eq!(
+            depths(Status::first()),
+            todo.map(|(title, depth)| (title.to_string(), depth))
+        );
+        assert_eq!(
+            depths(Status::first_closed()),
+            [("Bump the version".to_string(), 0)]
+        );
+        assert_eq!(lines[0], Line::Raw("1. Release".to_string()));
+    }
+}
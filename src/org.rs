@@ -0,0 +1,523 @@
+use crate::date::Date;
+use crate::item::{escape_title, parse_meta, parse_tags, unescape_title, Item, DUE_KEY, REC_KEY};
+use crate::recur::Recurrence;
+use crate::status::Status;
+
+// Key of the metadata map that holds the SCHEDULED date, DEADLINE is the due date.
+const SCHEDULED_KEY: &str = "scheduled";
+// Key of the metadata map that holds the tags of a headline, `* TODO title :work:urgent:`
+// has `tags:work:urgent`.
+const TAGS_KEY: &str = "tags";
+
+// Property that holds the creation time, as written by org-expiry.
+const CREATED_PROPERTY: &str = "CREATED";
+// Property that keeps the id of the items other items wait for. Non-numeric ids, like the
+// UUIDs of org-id, are kept as any other property.
+const ID_PROPERTY: &str = "ID";
+// Property that keeps the title, escaped like in the default format, when the headline
+// can't hold it: titles with line breaks, or that would read as a priority cookie.
+const TITLE_PROPERTY: &str = "TODO_RS_TITLE";
+
+const PROPERTIES: &str = ":PROPERTIES:";
+const END: &str = ":END:";
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+// Written in front of the parts of a title that would read as a priority cookie or as tags,
+// Emacs shows it as a space. The title itself is kept in TITLE_PROPERTY.
+const NO_BREAK_SPACE: char = '\u{a0}';
+
+// Returns the number of stars of a headline, `** Title` is at level 2.
+pub fn headline_level(line: &str) -> Option<usize> {
+    let level = line.len() - line.trim_start_matches('*').len();
+    (level > 0 && line[level..].starts_with(' ')).then_some(level)
+}
+
+// Returns the letter of a leading `[#A]` priority cookie and the title after it.
+fn split_priority(title: &str) -> Option<(char, &str)> {
+    match title.as_bytes() {
+        [b'[', b'#', letter @ b'A'..=b'Z', b']', ..] => Some((
+            *letter as char,
+            title[4..].strip_prefix(' ').unwrap_or(&title[4..]),
+        )),
+        _ => None,
+    }
+}
+
+// Returns the title before trailing `:work:urgent:` tags and the tags, `work:urgent`.
+fn split_tags(title: &str) -> Option<(&str, &str)> {
+    let title = title.trim_end_matches([' ', '\t']);
+    let (rest, group) = match title.rsplit_once([' ', '\t']) {
+        Some((rest, group)) => (rest.trim_end_matches([' ', '\t']), group),
+        None => ("", title),
+    };
+    let tags = group.strip_prefix(':')?.strip_suffix(':')?;
+    let tag_ok = |tag: &str| {
+        !tag.is_empty()
+            && tag
+                .chars()
+                .all(|c| c.is_alphanumeric() || "_@#%".contains(c))
+    };
+    tags.split(':').all(tag_ok).then_some((rest, tags))
+}
+
+// Reads a task headline, `* TODO title` or `* DONE title`, with an optional `[#A]` priority
+// cookie and `:tag:` groups at the end. The prefixes of the other columns are keywords as
+// well, `* IN-PROGRESS title`. Nested headlines are subtasks. Other headlines are not items.
+pub fn parse_item(line: &str) -> Option<Result<(Status, Item), String>> {
+    let level = headline_level(line)?;
+    let text = &line[level + 1..];
+    let (keyword, mut title) = text.split_once(' ').unwrap_or((text, ""));
+    let status = Status::all().find(|status| status.prefix() == keyword)?;
+    let mut item = Item::default();
+    if let Some((letter, rest)) = split_priority(title) {
+        item.priority = Some(letter);
+        title = rest;
+    }
+    if let Some((rest, tags)) = split_tags(title) {
+        item.meta.insert(TAGS_KEY.to_string(), tags.to_string());
+        title = rest;
+    }
+    // The title is kept as it is, `key:value` words are only metadata in the property drawer.
+    item.title = title.to_string();
+    item.tags = parse_tags(title);
+    item.depth = level - 1;
+    Some(Ok((status, item)))
+}
+
+// Reads the date of a timestamp, `<2026-10-20 Tue 10:00 +1w>` or `[2026-10-18 Sun]`,
+// along with the seconds since midnight and the repeater if it has them.
+fn parse_timestamp(text: &str) -> Option<(Date, u64, Option<&str>)> {
+    let inner = text
+        .strip_prefix('<')
+        .and_then(|text| text.strip_suffix('>'))
+        .or_else(|| {
+            text.strip_prefix('[')
+                .and_then(|text| text.strip_suffix(']'))
+        })?;
+    let mut words = inner.split_whitespace();
+    let date = Date::parse(words.next()?)?;
+    let mut seconds = 0;
+    let mut repeater = None;
+    for word in words {
+        if let Some((hours, minutes)) = word.split_once(':') {
+            let hours: u64 = hours.parse().ok()?;
+            let minutes: u64 = minutes.parse().ok()?;
+            seconds = hours * 3600 + minutes * 60;
+        } else if word.starts_with('+') || word.starts_with(".+") {
+            repeater = Some(word);
+        }
+    }
+    Some((date, seconds, repeater))
+}
+
+fn format_date(date: Date) -> String {
+    let weekday = (date.days() + 4).rem_euclid(7) as usize; // 1970-01-01 was a Thursday
+    format!("{} {}", date, WEEKDAYS[weekday])
+}
+
+// `[2026-10-18 Sun 10:00]` in local time.
+fn format_time(timestamp: u64) -> String {
+    let date = Date::from_timestamp(timestamp);
+    let seconds = timestamp.saturating_sub(date.timestamp());
+    format!(
+        "[{} {:02}:{:02}]",
+        format_date(date),
+        seconds / 3600,
+        seconds / 60 % 60
+    )
+}
+
+// Org repeaters that have a `rec:` equivalent are turned into it, others are kept as they are.
+fn parse_repeater(repeater: &str) -> String {
+    let rule = repeater
+        .strip_prefix('+')
+        .filter(|interval| !interval.is_empty())
+        .and_then(|interval| {
+            let unit = interval.chars().last()?;
+            let count = interval
+                .strip_suffix(unit)
+                .filter(|count| count.bytes().all(|b| b.is_ascii_digit()))?;
+            let count: u32 = count.parse().ok()?;
+            match (count, unit) {
+                (1, 'd') => Some("daily".to_string()),
+                (1, 'w') => Some("weekly".to_string()),
+                (1, 'm') => Some("monthly".to_string()),
+                (count, 'd') => Some(format!("{}d", count)),
+                (count, 'w') => Some(format!("{}d", count.checked_mul(7)?)),
+                _ => None,
+            }
+        });
+    rule.unwrap_or_else(|| repeater.to_string())
+}
+
+fn format_repeater(rule: &str) -> String {
+    match Recurrence::parse(rule) {
+        Some(Recurrence::Days(days)) if days.is_multiple_of(7) => format!("+{}w", days / 7),
+        Some(Recurrence::Days(days)) => format!("+{}d", days),
+        Some(Recurrence::Monthly(_)) => "+1m".to_string(),
+        None => rule.to_string(),
+    }
+}
+
+// Reads the `CLOSED:`, `DEADLINE:` and `SCHEDULED:` entries of a planning line into the item.
+fn parse_planning(line: &str, item: &mut Item) -> bool {
+    let mut rest = line.trim();
+    let mut entries = Vec::new();
+    while let Some((keyword, text)) = rest.split_once(": ") {
+        let close = match text.chars().next() {
+            Some('<') => '>',
+            Some('[') => ']',
+            _ => return false,
+        };
+        let Some(end) = text.find(close) else {
+            return false;
+        };
+        let Some(timestamp) = parse_timestamp(&text[..=end]) else {
+            return false;
+        };
+        entries.push((keyword, timestamp));
+        rest = text[end + 1..].trim_start();
+    }
+    let keywords_ok = entries
+        .iter()
+        .all(|(keyword, _)| ["CLOSED", "DEADLINE", "SCHEDULED"].contains(keyword));
+    if !rest.is_empty() || entries.is_empty() || !keywords_ok {
+        return false;
+    }
+    for (keyword, (date, seconds, repeater)) in entries {
+        match keyword {
+            "CLOSED" => item.completed = Some(date.timestamp() + seconds),
+            "DEADLINE" => {
+                item.meta.insert(DUE_KEY.to_string(), date.to_string());
+            }
+            _ => {
+                item.meta
+                    .insert(SCHEDULED_KEY.to_string(), date.to_string());
+            }
+        }
+        if let Some(repeater) = repeater {
+            item.meta
+                .insert(REC_KEY.to_string(), parse_repeater(repeater));
+        }
+    }
+    true
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+// Lines below a task headline belong to it, up to the next headline. Planning lines and
+// the properties todo-rs understands go into the fields of the item, any other line
+// (text, other drawers, properties with spaces in their value) is a note kept as it is.
+// A property drawer left empty is dropped from the notes, `format_item` writes it again
+// with the indentation of the planning line or of the drawer.
+pub fn parse_note(line: &str, item: &mut Item) -> bool {
+    if headline_level(line).is_some() {
+        return false;
+    }
+    if parse_planning(line, item) {
+        item.indent = indentation(line).to_string();
+        return true;
+    }
+    let text = line.trim();
+    if text == END
+        && item
+            .notes
+            .last()
+            .is_some_and(|note| note.trim() == PROPERTIES)
+    {
+        let drawer = item.notes.pop().unwrap_or_default();
+        item.indent = indentation(&drawer).to_string();
+        return true;
+    }
+    let property = text
+        .strip_prefix(':')
+        .and_then(|text| text.split_once(": "))
+        .map(|(key, value)| (key, value.trim()));
+    let id = property
+        .filter(|(key, _)| key.eq_ignore_ascii_case(ID_PROPERTY))
+        .and_then(|(_, value)| value.parse().ok());
+    if let Some(id) = id {
+        item.id = id;
+        return true;
+    }
+    match property {
+        Some((key, value)) if key.eq_ignore_ascii_case(TITLE_PROPERTY) => {
+            item.title = unescape_title(value);
+            item.tags = parse_tags(&item.title);
+        }
+        Some((key, value)) if key.eq_ignore_ascii_case(CREATED_PROPERTY) => {
+            match parse_timestamp(value) {
+                Some((date, seconds, _)) => item.created = Some(date.timestamp() + seconds),
+                None => item.notes.push(line.to_string()),
+            }
+        }
+        Some((key, value))
+            if !value.contains(char::is_whitespace)
+                && parse_meta(&format!("{}:{}", key, value)).is_some() =>
+        {
+            item.meta.insert(key.to_string(), value.to_string());
+        }
+        _ => item.notes.push(line.to_string()),
+    }
+    true
+}
+
+// Writes the headline at the depth of the item, followed by a planning line, the property
+// drawer and the notes. Like in the Markdown format the id is left out, unless it is kept
+// in the metadata. Line breaks of the title show as spaces in the headline, see TITLE_PROPERTY.
+pub fn format_item(status: &Status, item: &Item) -> String {
+    // Tags org-mode wouldn't read stay a property.
+    let mut meta = item.meta.clone();
+    let tags = meta
+        .get(TAGS_KEY)
+        .filter(|tags| split_tags(&format!(":{}:", tags)).is_some())
+        .cloned();
+    if tags.is_some() {
+        meta.remove(TAGS_KEY);
+    }
+    let mut title = item.title.replace(['\n', '\r'], " ");
+    if item.priority.is_none() && split_priority(&title).is_some() {
+        title.insert(0, NO_BREAK_SPACE);
+    }
+    if let (None, Some((rest, _))) = (&tags, split_tags(&title)) {
+        let group = title.trim_end_matches([' ', '\t'])[rest.len()..]
+            .trim_start_matches([' ', '\t'])
+            .to_string();
+        title = format!("{}{}{}", rest, NO_BREAK_SPACE, group);
+    }
+    let mut lines = format!("{} {}", "*".repeat(item.depth + 1), status.prefix());
+    if let Some(priority) = item.priority {
+        lines.push_str(&format!(" [#{}]", priority));
+    }
+    if !title.is_empty() {
+        lines.push_str(&format!(" {}", title));
+    }
+    if let Some(tags) = &tags {
+        lines.push_str(&format!(" :{}:", tags));
+    }
+    let whole_title = parse_item(&lines)
+        .and_then(Result::ok)
+        .is_some_and(|(_, read)| read.title == item.title);
+
+    // The repeat rule goes on the deadline, or on the scheduled date without one.
+    let mut planning = Vec::new();
+    if let Some(completed) = item.completed {
+        planning.push(format!("CLOSED: {}", format_time(completed)));
+    }
+    for (keyword, key) in [("DEADLINE", DUE_KEY), ("SCHEDULED", SCHEDULED_KEY)] {
+        let Some(date) = meta.get(key).and_then(|date| Date::parse(date)) else {
+            continue;
+        };
+        meta.remove(key);
+        match meta.remove(REC_KEY) {
+            Some(rule) => planning.push(format!(
+                "{}: <{} {}>",
+                keyword,
+                format_date(date),
+                format_repeater(&rule)
+            )),
+            None => planning.push(format!("{}: <{}>", keyword, format_date(date))),
+        }
+    }
+    if !planning.is_empty() {
+        lines.push_str(&format!("\n{}{}", item.indent, planning.join(" ")));
+    }
+
+    let mut properties = Vec::new();
+    if !whole_title {
+        properties.push(format!(
+            ":{}: {}",
+            TITLE_PROPERTY,
+            escape_title(&item.title)
+        ));
+    }
+    if let Some(created) = item.created {
+        properties.push(format!(":{}: {}", CREATED_PROPERTY, format_time(created)));
+    }
+    for (key, value) in meta.iter() {
+        properties.push(format!(":{}: {}", key, value));
+    }
+    // Properties go into the drawer kept in the notes, or into a new one in front of them,
+    // indented like the drawer.
+    let drawer = item.notes.iter().position(|note| note.trim() == PROPERTIES);
+    if drawer.is_none() && !properties.is_empty() {
+        lines.push_str(&format!("\n{}{}", item.indent, PROPERTIES));
+        for property in properties.iter() {
+            lines.push_str(&format!("\n{}{}", item.indent, property));
+        }
+        lines.push_str(&format!("\n{}{}", item.indent, END));
+    }
+    for (index, note) in item.notes.iter().enumerate() {
+        lines.push_str(&format!("\n{}", note));
+        if Some(index) == drawer {
+            for property in properties.iter() {
+                lines.push_str(&format!("\n{}{}", indentation(note), property));
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::{new_lists, Format};
+    use crate::storage::Storage;
+
+    fn parse(lines: &str) -> (Status, Item) {
+        let mut lines = lines.lines();
+        let (status, mut item) = parse_item(lines.next().unwrap()).unwrap().unwrap();
+        for line in lines {
+            assert!(parse_note(line, &mut item), "not a note: {}", line);
+        }
+        (status, item)
+    }
+
+    #[test]
+    fn reads_headlines() {
+        let (status, item) = parse("** TODO [#B] Call +bob @phone");
+        assert_eq!(status, Status::first());
+        assert_eq!(item.title, "Call +bob @phone");
+        assert_eq!(item.priority, Some('B'));
+        assert_eq!(item.depth, 1);
+        assert_eq!(item.tags, ["+bob", "@phone"]);
+        assert_eq!(parse("* DONE").1.title, "");
+        assert!(parse_item("* Notes").is_none());
+        assert!(parse_item("*bold* TODO").is_none());
+        assert!(parse_item("TODO not a headline").is_none());
+    }
+
+    #[test]
+    fn reads_planning_and_properties() {
+        let (status, item) = parse(
+            "* DONE Ship it\n\
+             CLOSED: [2026-10-18 Sun 10:30] DEADLINE: <2026-10-20 Tue +2w>\n\
+             :PROPERTIES:\n\
+             :ID: 7\n\
+             :effort: 2h\n\
+             :END:\n\
+             Some notes",
+        );
+        assert!(status.is_closed());
+        assert_eq!(item.id, 7);
+        assert_eq!(
+            item.completed,
+            Some(Date::parse("2026-10-18").unwrap().timestamp() + 10 * 3600 + 30 * 60)
+        );
+        assert_eq!(
+            item.meta.get(DUE_KEY).map(String::as_str),
+            Some("2026-10-20")
+        );
+        assert_eq!(item.meta.get(REC_KEY).map(String::as_str), Some("14d"));
+        assert_eq!(item.meta.get("effort").map(String::as_str), Some("2h"));
+        assert_eq!(item.notes, ["Some notes"]);
+    }
+
+    #[test]
+    fn keeps_planning_lines_it_does_not_know_as_notes() {
+        let (_, item) = parse("* TODO x\nDEADLINE: <2026-02-30 Mon>\nDEADLINE: soon");
+        assert!(item.meta.is_empty());
+        assert_eq!(item.notes, ["DEADLINE: <2026-02-30 Mon>", "DEADLINE: soon"]);
+    }
+
+    #[test]
+    fn repeaters() {
+        assert_eq!(parse_repeater("+1d"), "daily");
+        assert_eq!(parse_repeater("+1w"), "weekly");
+        assert_eq!(parse_repeater("+1m"), "monthly");
+        assert_eq!(parse_repeater("+3d"), "3d");
+        assert_eq!(parse_repeater("+2w"), "14d");
+        for kept in ["+", "+w", "+1y", ".+1d", "++1w", "+999999999w", "+€"] {
+            assert_eq!(parse_repeater(kept), kept);
+        }
+        for rule in ["daily", "weekly", "3d", "14d", "monthly"] {
+            assert_eq!(parse_repeater(&format_repeater(rule)), rule);
+        }
+    }
+
+    #[test]
+    fn format_reads_back() {
+        let mut item = Item::new(3, "Ship it");
+        item.priority = Some('A');
+        item.depth = 2;
+        item.created = Some(Date::parse("2026-10-17").unwrap().timestamp() + 8 * 3600);
+        item.completed = Some(Date::parse("2026-10-18").unwrap().timestamp() + 9 * 3600);
+        item.meta
+            .insert(DUE_KEY.to_string(), "2026-10-20".to_string());
+        item.meta.insert(REC_KEY.to_string(), "weekly".to_string());
+        item.notes = vec!["a note".to_string()];
+        let status = Status::first_closed();
+        let text = format_item(&status, &item);
+        assert!(text.starts_with("*** DONE [#A] Ship it\nCLOSED: [2026-10-18 Sun 09:00] DEADLINE: <2026-10-20 Tue +1w>\n"));
+        let (read_status, read) = parse(&text);
+        assert_eq!(read_status, status);
+        assert_eq!(Item { id: 3, ..read }, item);
+    }
+
+    #[test]
+    fn reads_tags() {
+        let (_, item) = parse("* TODO Ship it   :work:@office:");
+        assert_eq!(item.title, "Ship it");
+        assert_eq!(
+            item.meta.get(TAGS_KEY).map(String::as_str),
+            Some("work:@office")
+        );
+        assert_eq!(parse("* TODO :work:").1.title, "");
+        for title in ["time at 10:30", "a :b c:", "empty ::", "odd :a-b:"] {
+            let (_, item) = parse(&format!("* TODO {}", title));
+            assert_eq!(item.title, title);
+            assert!(item.meta.is_empty());
+        }
+    }
+
+    #[test]
+    fn titles_the_headline_cannot_hold() {
+        for title in [
+            "two\nlines",
+            "[#A] not a priority",
+            " padded ",
+            "ends with :tags:",
+            ":only:",
+        ] {
+            for tags in [None, Some("work")] {
+                let mut item = Item {
+                    created: None,
+                    ..Item::new(1, title)
+                };
+                if let Some(tags) = tags {
+                    item.meta.insert(TAGS_KEY.to_string(), tags.to_string());
+                }
+                let (_, read) = parse(&format_item(&Status::first(), &item));
+                assert_eq!(Item { id: 1, ..read }, item, "{:?}", title);
+            }
+        }
+    }
+
+    #[test]
+    fn files_keep_their_indentation() {
+        let text = "#+TODO: TODO IN-PROGRESS BLOCKED | DONE CANCELLED\n\
+                    * Project\n\
+                    ** TODO [#A] Ship it :work:urgent:\n   \
+                       DEADLINE: <2026-10-20 Tue>\n   \
+                       :PROPERTIES:\n   \
+                       :effort: 2h\n   \
+                       :END:\n   \
+                       Some notes\n\
+                    ** DONE Old :home:\n   \
+                       CLOSED: [2026-10-18 Sun 10:30]\n   \
+                       :PROPERTIES:\n   \
+                       :ORDERED: t\n   \
+                       :END:\n";
+        let mut lists = new_lists();
+        let mut lines = Vec::new();
+        let mut errors = Vec::new();
+        Format::Org
+            .load(text, &mut lists, &mut lines, &mut errors)
+            .unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(Format::Org.format(&lists, &lines), text);
+    }
+}
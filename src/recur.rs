@@ -0,0 +1,48 @@
+use crate::date::{days_in_month, Date};
+
+// enum Recurrence is the rule of a repeating item, written as `rec:<rule>`:
+//      `daily`, `weekly`, `<N>d` for every N days,
+//      `monthly` for the same day every month and `monthly-<D>` for day D of every month.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Recurrence {
+    Days(u32),
+    Monthly(Option<u32>),
+}
+
+impl Recurrence {
+    pub fn parse(rule: &str) -> Option<Self> {
+        match rule {
+            "daily" => Some(Recurrence::Days(1)),
+            "weekly" => Some(Recurrence::Days(7)),
+            "monthly" => Some(Recurrence::Monthly(None)),
+            _ => {
+                if let Some(days) = rule.strip_suffix('d') {
+                    let days = days.parse().ok()?;
+                    (days > 0).then_some(Recurrence::Days(days))
+                } else {
+                    let day = rule.strip_prefix("monthly-")?.parse().ok()?;
+                    (1..=31)
+                        .contains(&day)
+                        .then_some(Recurrence::Monthly(Some(day)))
+                }
+            }
+        }
+    }
+
+    // next : The occurrence right after `date`. Monthly rules fall back
+    // to the last day of the month when it is shorter than the wanted day.
+    pub fn next(&self, date: Date) -> Date {
+        match *self {
+            Recurrence::Days(days) => date.add_days(days as i64),
+            Recurrence::Monthly(day) => {
+                let (year, month) = if date.month == 12 {
+                    (date.year + 1, 1)
+                } else {
+                    (date.year, date.month + 1)
+                };
+                let day = day.unwrap_or(date.day).min(days_in_month(year, month));
+                Date { year, month, day }
+            }
+        }
+    }
+}
@@ -1,43 +1,409 @@
-// Defines enum called Status and Implements a function toggle in it.
-#[derive(Debug, PartialEq)]
-pub enum Status {
+use crate::board::{self, Column};
+use crate::item::Item;
+use crate::markdown;
+use crate::org;
+use crate::todotxt;
+use std::collections::HashSet;
+
+// Number of spaces that nest an item one level deeper in the file.
+const INDENT_WIDTH: usize = 2;
+
+// enum Format lists the syntaxes the lines of the TODO file can be written in,
+// see `storage::detect` for how it is picked.
+//      Todo : `TODO: title`, `DONE: title` and the prefixes of the other columns, the default.
+//      Markdown : GitHub task lists, `- [ ] title` and `- [x] title`.
+//      TodoTxt : the todo.txt format, `title` and `x title`.
+//      Org : Emacs org-mode headlines, `* TODO title`, `* DONE title` and the other states.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
     Todo,
-    Done,
+    Markdown,
+    TodoTxt,
+    Org,
 }
 
-// Function that toggles between status
-// toggle : It is used to switch the states of the task.
-impl Status {
-    pub fn toggle(&self) -> Self {
+impl Format {
+    pub fn parse_item(&self, line: &str) -> Option<Result<(Status, Item), String>> {
+        match self {
+            Format::Todo => parse_item(line),
+            Format::Markdown => markdown::parse_item(line),
+            Format::TodoTxt => todotxt::parse_item(line),
+            Format::Org => org::parse_item(line),
+        }
+    }
+
+    pub fn format_item(&self, status: &Status, item: &Item) -> String {
+        match self {
+            Format::Todo => format_item(status, item),
+            Format::Markdown => markdown::format_item(status, item),
+            Format::TodoTxt => todotxt::format_item(status, item),
+            Format::Org => org::format_item(status, item),
+        }
+    }
+
+    // parse_note : Attaches a line that follows an item to it, as a note or, in org files,
+    // as a planning line or a property. Returns false when the line does not belong to the item.
+    pub fn parse_note(&self, line: &str, item: &mut Item) -> bool {
+        let note = match self {
+            Format::Todo | Format::Markdown => parse_note(line, item.depth, ""),
+            Format::TodoTxt => parse_note(line, item.depth, todotxt::NOTE_MARKER),
+            Format::Org => return org::parse_note(line, item),
+        };
+        match note {
+            Some(note) => {
+                item.notes.push(note.to_string());
+                true
+            }
+            None => false,
+        }
+    }
+
+    // section_depth : For lines that are headlines but not items, the depth of the items
+    // nested under them.
+    pub fn section_depth(&self, line: &str) -> Option<usize> {
         match self {
-            Status::Todo => Status::Done,
-            Status::Done => Status::Todo,
+            Format::Org => org::headline_level(line),
+            _ => None,
         }
     }
 }
 
+// Defines struct Status, the column of the board an item is in (see `board::columns`).
+// Each column has its own list and panel, `index` is its position on the board.
+// Items of closed columns, like DONE and CANCELLED, are finished.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Status(usize);
+
+impl Status {
+    // All the columns in the order of their panels.
+    pub fn all() -> impl Iterator<Item = Status> {
+        (0..board::columns().len()).map(Status)
+    }
+
+    pub fn count() -> usize {
+        board::columns().len()
+    }
+
+    // first : The column new items and the next occurrences of repeating items go to.
+    pub fn first() -> Self {
+        Status(0)
+    }
+
+    // first_closed : The column of completed items, DONE on the default board.
+    pub fn first_closed() -> Self {
+        Status::all().find(Status::is_closed).unwrap_or(Status::first())
+    }
+
+    // find : The column with the given prefix, if the board has it.
+    pub fn find(prefix: &str) -> Option<Self> {
+        Status::all().find(|status| status.prefix() == prefix)
+    }
+
+    pub fn index(&self) -> usize {
+        self.0
+    }
+
+    fn column(&self) -> &'static Column {
+        &board::columns()[self.0]
+    }
+
+    // name : The panel header.
+    pub fn name(&self) -> &'static str {
+        &self.column().name
+    }
+
+    // prefix : Marks the items in the file, `TODO:`, and is the keyword of the other formats.
+    pub fn prefix(&self) -> &'static str {
+        &self.column().prefix
+    }
+
+    // mark : The character in the checkbox of its items, `[ ]` or `[x]`.
+    pub fn mark(&self) -> char {
+        self.column().mark
+    }
 
-pub fn parse_item(line: &str) -> Option<(Status, &str)> {
-    let todo_item = line
-        .strip_prefix("TODO: ")
-        .map(|title| (Status::Todo, title));
-    let done_item = line
-        .strip_prefix("DONE: ")
-        .map(|title| (Status::Done, title));
-    todo_item.or(done_item)
+    pub fn color(&self) -> i16 {
+        self.column().color
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.column().closed
+    }
+
+    // advance : Where Enter moves the items to, the `next:` column or the one after this one.
+    // Items of the last column go back to the first one.
+    pub fn advance(&self) -> Self {
+        match &self.column().next {
+            Some(next) => Status::find(next).unwrap_or(Status::first()),
+            None => self.next().unwrap_or(Status::first()),
+        }
+    }
+
+    // next : The column to the right of this one, if any.
+    pub fn next(&self) -> Option<Self> {
+        (self.0 + 1 < Status::count()).then_some(Status(self.0 + 1))
+    }
+
+    // prev : The column to the left of this one, if any.
+    pub fn prev(&self) -> Option<Self> {
+        self.0.checked_sub(1).map(Status)
+    }
+
+    // cycle : The panel after this one, wrapping around.
+    pub fn cycle(&self) -> Self {
+        self.next().unwrap_or(Status::first())
+    }
+
+    // cycle_back : The panel before this one, wrapping around.
+    pub fn cycle_back(&self) -> Self {
+        self.prev().unwrap_or(Status(Status::count() - 1))
+    }
 }
 
-pub fn list_drag_up(list: &mut [String], list_curr: &mut usize) {
-    if *list_curr > 0 {
-        list.swap(*list_curr, *list_curr - 1);
-        *list_curr -= 1;
+// Returns one empty list per column, indexed by `Status::index`.
+pub fn new_lists() -> Vec<Vec<Item>> {
+    vec![Vec::new(); Status::count()]
+}
+
+// Borrows the lists of two different columns at once.
+pub fn lists_pair(lists: &mut [Vec<Item>], first: Status, second: Status) -> (&mut Vec<Item>, &mut Vec<Item>) {
+    assert_ne!(first, second, "the lists of a pair must differ");
+    if first.index() < second.index() {
+        let (head, tail) = lists.split_at_mut(second.index());
+        (&mut head[first.index()], &mut tail[0])
+    } else {
+        let (head, tail) = lists.split_at_mut(first.index());
+        (&mut tail[0], &mut head[second.index()])
     }
 }
 
-pub fn list_drag_down(list: &mut [String], list_curr: &mut usize) {
-    if *list_curr + 1 < list.len() {
-        list.swap(*list_curr, *list_curr + 1);
-        *list_curr += 1;
+// open_prerequisites : The items of the open columns that the item waits for.
+pub fn open_prerequisites<'a>(lists: &'a [Vec<Item>], item: &Item) -> Vec<&'a Item> {
+    let ids = item.prerequisites();
+    Status::all()
+        .filter(|status| !status.is_closed())
+        .flat_map(|status| lists[status.index()].iter())
+        .filter(|other| ids.contains(&other.id))
+        .collect()
+}
+
+// waits_for : Whether the item `from` waits for the item `to`, directly or through other items.
+pub fn waits_for(lists: &[Vec<Item>], from: u64, to: u64) -> bool {
+    let mut seen = HashSet::new();
+    let mut pending = vec![from];
+    while let Some(id) = pending.pop() {
+        if id == to {
+            return true;
+        }
+        if seen.insert(id) {
+            if let Some(item) = lists.iter().flatten().find(|item| item.id == id) {
+                pending.extend(item.prerequisites());
+            }
+        }
+    }
+    false
+}
+
+// forget_prerequisites : Items stop waiting for the given ones once these leave the lists,
+// their ids could be given to new items.
+pub fn forget_prerequisites(lists: &mut [Vec<Item>], ids: &HashSet<u64>) {
+    for item in lists.iter_mut().flatten() {
+        let prerequisites = item.prerequisites();
+        if prerequisites.iter().any(|id| ids.contains(id)) {
+            let kept: Vec<u64> = prerequisites.into_iter().filter(|id| !ids.contains(id)).collect();
+            item.set_prerequisites(&kept);
+        }
+    }
+}
+
+// enum Line describes a line of the TODO file. Item lines mark the places where the items
+// of each list are written back in order, any other line (comments, blank lines,
+// lines of other tools) is kept as it was.
+// Section lines take no room in the file, they mark how many levels deep the items that follow
+// are nested under a headline of the file (see `Format::section_depth`).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    Item(Status),
+    Raw(String),
+    Section(usize),
+}
+
+// struct ParseError is a line that starts like an item but could not be parsed.
+// `line` is its index in the file layout, `number` its 1-based line number.
+pub struct ParseError {
+    pub line: usize,
+    pub number: usize,
+    pub reason: String,
+}
+
+// Returns None for lines that are not items at all, and the reason for lines that
+// start with a status prefix like `TODO:` but are ill-formed. Leading spaces give the depth of the item,
+// subtasks follow their parent indented by INDENT_WIDTH more spaces.
+pub fn parse_item(line: &str) -> Option<Result<(Status, Item), String>> {
+    let text = line.trim_start_matches(' ');
+    let indent = line.len() - text.len();
+    let (status, text) = Status::all().find_map(|status| {
+        let text = text.strip_prefix(status.prefix())?.strip_prefix(':')?;
+        Some((status, text))
+    })?;
+    Some(parse_item_text(status, indent, text))
+}
+
+// Shared by the line formats: `indent` is the number of leading spaces of the line
+// and `text` what follows the status marker of the item.
+pub fn parse_item_text(status: Status, indent: usize, text: &str) -> Result<(Status, Item), String> {
+    let depth = parse_depth(indent)?;
+    // An empty title may have lost its trailing space to an editor.
+    let text = match text.strip_prefix(' ') {
+        Some(text) => text,
+        None if text.is_empty() => text,
+        None => return Err("missing space after the status".to_string()),
+    };
+    let mut item = Item::parse(text);
+    item.depth = depth;
+    Ok((status, item))
+}
+
+// Turns the number of leading spaces of an item line into its depth.
+pub fn parse_depth(indent: usize) -> Result<usize, String> {
+    if indent.is_multiple_of(INDENT_WIDTH) {
+        Ok(indent / INDENT_WIDTH)
+    } else {
+        Err(format!(
+            "indented by {} spaces, not a multiple of {}",
+            indent, INDENT_WIDTH
+        ))
+    }
+}
+
+// Notes of an item are the lines right after it that are indented one level deeper
+// than the item and are not items themselves. Formats where such a line would be an item
+// start notes with a `note_marker` followed by a space.
+pub fn parse_note<'a>(line: &'a str, depth: usize, note_marker: &str) -> Option<&'a str> {
+    let note = line.strip_prefix(" ".repeat((depth + 1) * INDENT_WIDTH).as_str())?;
+    if note_marker.is_empty() {
+        return Some(note.strip_prefix('\\').filter(|note| is_escaped(note)).unwrap_or(note));
+    }
+    let note = note.strip_prefix(note_marker)?;
+    note.strip_prefix(' ').or(note.is_empty().then_some(note))
+}
+
+pub fn format_item(status: &Status, item: &Item) -> String {
+    format_lines(item, &format!("{}: {}", status.prefix(), item.format()), "")
+}
+
+// Without a note marker, notes that would read as an item are written behind a backslash,
+// as are the ones that would lose their own leading backslash to it.
+fn is_escaped(note: &str) -> bool {
+    match note.strip_prefix('\\') {
+        Some(rest) => is_escaped(rest),
+        None => parse_item(note).is_some() || markdown::parse_item(note).is_some(),
+    }
+}
+
+// Shared by the line formats: indents the item line to the depth of the item
+// and puts its notes after it, one line each.
+pub fn format_lines(item: &Item, line: &str, note_marker: &str) -> String {
+    let mut lines = format!("{}{}", " ".repeat(item.depth * INDENT_WIDTH), line);
+    let note_indent = " ".repeat((item.depth + 1) * INDENT_WIDTH);
+    for note in item.notes.iter() {
+        if note_marker.is_empty() && is_escaped(note) {
+            lines.push_str(&format!("\n{}\\{}", note_indent, note));
+        } else if note_marker.is_empty() {
+            lines.push_str(&format!("\n{}{}", note_indent, note));
+        } else if note.is_empty() {
+            lines.push_str(&format!("\n{}{}", note_indent, note_marker));
+        } else {
+            lines.push_str(&format!("\n{}{} {}", note_indent, note_marker, note));
+        }
+    }
+    lines
+}
+
+// Subtasks are stored right after their parent with a greater depth.
+// Returns the index one past the last subtask of the item at `index`.
+pub fn subtree_end(list: &[Item], index: usize) -> usize {
+    let depth = list[index].depth;
+    index + 1
+        + list[index + 1..]
+            .iter()
+            .take_while(|item| item.depth > depth)
+            .count()
+}
+
+pub fn list_parent(list: &[Item], index: usize) -> Option<usize> {
+    (0..index).rev().find(|&parent| list[parent].depth < list[index].depth)
+}
+
+// Makes sure no item is nested more than one level deeper than the item above it,
+// e.g. after its parent was removed the subtasks move up to the parent's level.
+pub fn normalize_depths(list: &mut [Item]) {
+    let mut max_depth = 0;
+    for item in list.iter_mut() {
+        item.depth = item.depth.min(max_depth);
+        max_depth = item.depth + 1;
+    }
+}
+
+// Removes the item at `index`, its subtasks stay behind one level up.
+pub fn list_remove(list: &mut Vec<Item>, index: usize) -> Item {
+    let end = subtree_end(list, index);
+    for item in list[index + 1..end].iter_mut() {
+        item.depth -= 1;
+    }
+    let item = list.remove(index);
+    normalize_depths(list);
+    item
+}
+
+// Swaps the current subtree with the sibling subtree above it.
+pub fn list_drag_up(list: &mut [Item], list_curr: &mut usize) {
+    let start = *list_curr;
+    if start >= list.len() {
+        return;
+    }
+    let depth = list[start].depth;
+    // The closest item above that is not deeper is either the previous sibling or the parent.
+    if let Some(prev) = (0..start).rev().find(|&index| list[index].depth <= depth) {
+        if list[prev].depth == depth {
+            let end = subtree_end(list, start);
+            list[prev..end].rotate_left(start - prev);
+            *list_curr = prev;
+        }
+    }
+}
+
+// Swaps the current subtree with the sibling subtree below it.
+pub fn list_drag_down(list: &mut [Item], list_curr: &mut usize) {
+    let start = *list_curr;
+    if start >= list.len() {
+        return;
+    }
+    let end = subtree_end(list, start);
+    if end < list.len() && list[end].depth == list[start].depth {
+        let next_end = subtree_end(list, end);
+        list[start..next_end].rotate_left(end - start);
+        *list_curr = start + next_end - end;
+    }
+}
+
+// Makes the current subtree a subtask of the sibling above it.
+pub fn list_indent(list: &mut [Item], list_curr: usize) {
+    if list_curr > 0 && list_curr < list.len() && list[list_curr - 1].depth >= list[list_curr].depth {
+        let end = subtree_end(list, list_curr);
+        for item in list[list_curr..end].iter_mut() {
+            item.depth += 1;
+        }
+    }
+}
+
+// Moves the current subtree one level up.
+pub fn list_outdent(list: &mut [Item], list_curr: usize) {
+    if list_curr < list.len() && list[list_curr].depth > 0 {
+        let end = subtree_end(list, list_curr);
+        for item in list[list_curr..end].iter_mut() {
+            item.depth -= 1;
+        }
     }
 }
 
@@ -47,7 +413,7 @@ pub fn list_up(list_curr: &mut usize) {
     }
 }
 
-pub fn list_down(list: &[String], list_curr: &mut usize) {
+pub fn list_down<T>(list: &[T], list_curr: &mut usize) {
     if *list_curr + 1 < list.len() {
         *list_curr += 1;
     }
@@ -59,21 +425,178 @@ pub fn list_first(list_curr: &mut usize) {
     }
 }
 
-pub fn list_last(list: &[String], list_curr: &mut usize) {
+pub fn list_last<T>(list: &[T], list_curr: &mut usize) {
     if !list.is_empty() {
         *list_curr = list.len() - 1;
     }
 }
 
-pub fn list_transfer(
-    list_dst: &mut Vec<String>,
-    list_src: &mut Vec<String>,
-    list_src_curr: &mut usize,
+// Sorts the items of the view by the given key. Each item is compared by the keys of its
+// ancestors and then its own, followed by the index to keep the manual order among equals,
+// so siblings get sorted among themselves and subtasks stay right under their parent.
+pub fn view_sort_by_key<K: Ord + Clone>(list: &[Item], view: &mut [usize], key: impl Fn(&Item) -> K) {
+    let mut path: Vec<(K, usize)> = Vec::new();
+    let mut paths = Vec::new();
+    for (index, item) in list.iter().enumerate() {
+        path.truncate(item.depth);
+        path.push((key(item), index));
+        paths.push(path.clone());
+    }
+    view.sort_by(|&a, &b| paths[a].cmp(&paths[b]));
+}
+
+// Sorts the list by the given key while keeping the cursor on the same item.
+pub fn list_sort_by_key<K: Ord + Clone>(list: &mut [Item], list_curr: &mut usize, key: impl Fn(&Item) -> K) {
+    let curr_id = list.get(*list_curr).map(|item| item.id);
+    let mut order: Vec<usize> = (0..list.len()).collect();
+    view_sort_by_key(list, &mut order, key);
+    let sorted: Vec<Item> = order.iter().map(|&index| list[index].clone()).collect();
+    list.clone_from_slice(&sorted);
+    if let Some(index) = list.iter().position(|item| Some(item.id) == curr_id) {
+        *list_curr = index;
+    }
+}
+
+// A view is a list of indices into a list, in the order they are displayed.
+// The cursor keeps pointing into the list itself, so actions like list_transfer
+// work unchanged while k/j/g/G move along the view.
+pub fn view_navigate(
+    view: &[usize],
+    list_curr: &mut usize,
+    navigate: impl FnOnce(&[usize], &mut usize),
 ) {
-    if *list_src_curr < list_src.len() {
-        list_dst.push(list_src.remove(*list_src_curr));
-        if *list_src_curr >= list_src.len() && !list_src.is_empty() {
-            *list_src_curr = list_src.len() - 1;
+    let mut view_curr = view.iter().position(|&index| index == *list_curr).unwrap_or(0);
+    navigate(view, &mut view_curr);
+    if let Some(&index) = view.get(view_curr) {
+        *list_curr = index;
+    }
+}
+
+// Moves the cursor to the first item of the view if the item under it got hidden.
+pub fn view_snap(view: &[usize], list_curr: &mut usize) {
+    if !view.contains(list_curr) {
+        if let Some(&index) = view.first() {
+            *list_curr = index;
         }
     }
 }
+
+// Every item except the subtasks of collapsed items, in list order.
+pub fn tree_view(list: &[Item], collapsed: &HashSet<u64>) -> Vec<usize> {
+    let mut view = Vec::new();
+    let mut index = 0;
+    while index < list.len() {
+        view.push(index);
+        if collapsed.contains(&list[index].id) {
+            index = subtree_end(list, index);
+        } else {
+            index += 1;
+        }
+    }
+    view
+}
+
+// Hides the subtasks of the current item. If there is nothing to hide,
+// jumps to the parent instead so that h can be used to walk up the tree.
+pub fn list_collapse(list: &[Item], list_curr: &mut usize, collapsed: &mut HashSet<u64>) {
+    if *list_curr >= list.len() {
+        return;
+    }
+    let has_subtasks = subtree_end(list, *list_curr) > *list_curr + 1;
+    if has_subtasks && !collapsed.contains(&list[*list_curr].id) {
+        collapsed.insert(list[*list_curr].id);
+    } else if let Some(parent) = list_parent(list, *list_curr) {
+        *list_curr = parent;
+    }
+}
+
+pub fn list_expand(list: &[Item], list_curr: usize, collapsed: &mut HashSet<u64>) {
+    if let Some(item) = list.get(list_curr) {
+        collapsed.remove(&item.id);
+    }
+}
+
+// Moves only the current item, its subtasks stay behind one level up.
+// Returns how many items were moved.
+pub fn list_transfer(
+    list_dst: &mut Vec<Item>,
+    list_src: &mut Vec<Item>,
+    list_src_curr: &mut usize,
+) -> usize {
+    if *list_src_curr >= list_src.len() {
+        return 0;
+    }
+    let mut item = list_remove(list_src, *list_src_curr);
+    item.depth = 0;
+    list_dst.push(item);
+    if *list_src_curr >= list_src.len() && !list_src.is_empty() {
+        *list_src_curr = list_src.len() - 1;
+    }
+    1
+}
+
+// Moves the current item together with all its subtasks, returns how many items were moved.
+pub fn list_transfer_subtree(
+    list_dst: &mut Vec<Item>,
+    list_src: &mut Vec<Item>,
+    list_src_curr: &mut usize,
+) -> usize {
+    let start = *list_src_curr;
+    if start >= list_src.len() {
+        return 0;
+    }
+    let end = subtree_end(list_src, start);
+    let depth = list_src[start].depth;
+    list_dst.extend(list_src.drain(start..end).map(|mut item| {
+        item.depth -= depth;
+        item
+    }));
+    if *list_src_curr >= list_src.len() && !list_src.is_empty() {
+        *list_src_curr = list_src.len() - 1;
+    }
+    end - start
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(depths: &[(&str, usize)]) -> Vec<Item> {
+        (1..)
+            .zip(depths)
+            .map(|(id, &(title, depth))| Item { depth, ..Item::new(id, title) })
+            .collect()
+    }
+
+    fn depths(list: &[Item]) -> Vec<(&str, usize)> {
+        list.iter().map(|item| (item.title.as_str(), item.depth)).collect()
+    }
+
+    #[test]
+    fn transfer_leaves_subtasks_one_level_up() {
+        let mut src = list(&[("A", 0), ("B", 0), ("B1", 1), ("B1a", 2), ("C", 0)]);
+        let mut dst = Vec::new();
+        let mut curr = 1;
+        assert_eq!(list_transfer(&mut dst, &mut src, &mut curr), 1);
+        assert_eq!(depths(&dst), [("B", 0)]);
+        assert_eq!(depths(&src), [("A", 0), ("B1", 0), ("B1a", 1), ("C", 0)]);
+        assert_eq!(list_parent(&src, 1), None);
+    }
+
+    #[test]
+    fn remove_nested_item_keeps_siblings() {
+        let mut list = list(&[("A", 0), ("A1", 1), ("A1a", 2), ("A2", 1)]);
+        list_remove(&mut list, 1);
+        assert_eq!(depths(&list), [("A", 0), ("A1a", 1), ("A2", 1)]);
+    }
+
+    #[test]
+    fn transfer_subtree_moves_subtasks() {
+        let mut src = list(&[("A", 0), ("A1", 1), ("A1a", 2), ("B", 0)]);
+        let mut dst = Vec::new();
+        let mut curr = 1;
+        assert_eq!(list_transfer_subtree(&mut dst, &mut src, &mut curr), 2);
+        assert_eq!(depths(&dst), [("A1", 0), ("A1a", 1)]);
+        assert_eq!(depths(&src), [("A", 0), ("B", 0)]);
+    }
+}
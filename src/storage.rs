@@ -0,0 +1,426 @@
+use crate::board;
+use crate::ical;
+use crate::item::{next_id, Item};
+use crate::json;
+use crate::markdown;
+use crate::status::{normalize_depths, Format, Line, ParseError, Status};
+use crate::version;
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Number of lines looked at to guess the format of a file without a known extension.
+const SNIFF_LINES: usize = 20;
+
+// trait Storage is a file format the lists can be loaded from and saved to.
+//      parse : Reads the text of the file into the lists, one per state indexed by `Status::index`.
+//              `lines` is the layout of the file
+//              for formats that keep other content around, `errors` the ill-formed item lines.
+//      format : Inverse of `parse`, renders the lists into the layout.
+// New formats implement these two and get added to `detect`.
+pub trait Storage {
+    fn name(&self) -> &'static str;
+
+    fn parse(
+        &self,
+        text: &str,
+        lists: &mut [Vec<Item>],
+        lines: &mut Vec<Line>,
+        errors: &mut Vec<ParseError>,
+    ) -> Result<(), String>;
+
+    fn format(&self, lists: &[Vec<Item>], lines: &[Line]) -> String;
+
+    // load : Parses the text, then promotes orphaned subtasks and gives ids to the items
+    // that have none, as files written before items had ids or formats that leave them out.
+    fn load(
+        &self,
+        text: &str,
+        lists: &mut [Vec<Item>],
+        lines: &mut Vec<Line>,
+        errors: &mut Vec<ParseError>,
+    ) -> Result<(), String> {
+        self.parse(text, lists, lines, errors)?;
+        for list in lists.iter_mut() {
+            normalize_depths(list);
+        }
+
+        let mut id = next_id(lists.iter().flatten());
+        for item in lists.iter_mut().flatten() {
+            if item.id == 0 {
+                item.id = id;
+                id += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&self, file_path: &str, lists: &[Vec<Item>], lines: &[Line]) -> io::Result<()> {
+        write_atomic(file_path, &self.format(lists, lines))
+    }
+}
+
+// write_atomic : Writes the text to a temporary file next to the file, flushes it to the disk
+// and renames it over the file, so that a failed save leaves the file as it was.
+// The new file keeps the permissions of the old one, and a symlink keeps pointing to it.
+pub fn write_atomic(file_path: &str, text: &str) -> io::Result<()> {
+    let path = fs::canonicalize(file_path).unwrap_or_else(|_| PathBuf::from(file_path));
+    let path = path.as_path();
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not a file path"))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", name.to_string_lossy()));
+    let written = (|| {
+        let mut temp = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp_path)?;
+        if let Ok(metadata) = fs::metadata(path) {
+            temp.set_permissions(metadata.permissions())?;
+        }
+        temp.write_all(text.as_bytes())?;
+        temp.sync_all()?;
+        fs::rename(&temp_path, path)
+    })();
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return written;
+    }
+    // The rename itself only lasts once the directory is flushed, not every system allows it.
+    let dir = path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+// The line formats write the items into the item lines of the loaded file, in order.
+// Any other line of the file is kept as it is.
+impl Storage for Format {
+    fn name(&self) -> &'static str {
+        match self {
+            Format::Todo => "todo",
+            Format::Markdown => "Markdown",
+            Format::TodoTxt => "todo.txt",
+            Format::Org => "org-mode",
+        }
+    }
+
+    // Ill-formed item lines don't stop the loading, they are kept as they are
+    // and reported in `errors` so the user can decide what to do with them.
+    fn parse(
+        &self,
+        text: &str,
+        lists: &mut [Vec<Item>],
+        lines: &mut Vec<Line>,
+        errors: &mut Vec<ParseError>,
+    ) -> Result<(), String> {
+        // Only the default format has versions, older files are read in the current syntax.
+        let version = match self {
+            Format::Todo => version::parse_header(text.lines().next())?,
+            _ => None,
+        };
+        let mut last: Option<Status> = None; // List of the previous item, notes lines belong to it
+        let mut section = 0; // Depth of the items under the current headline of the file
+        let mut indents = Vec::new(); // Indentation of the Markdown items above, see `markdown::nest`
+        for (index, line) in text
+            .lines()
+            .enumerate()
+            .skip(usize::from(version.is_some()))
+        {
+            let line = match self {
+                Format::Todo => version::migrate_line(version, line),
+                _ => line.to_string(),
+            };
+            let line = line.as_str();
+            match self.parse_item(line) {
+                Some(Ok((status, mut item))) => {
+                    if *self == Format::Markdown {
+                        item.depth = markdown::nest(&mut indents, item.depth);
+                    }
+                    // An item as shallow as the headline above it ends its section.
+                    if item.depth < section {
+                        section = item.depth;
+                        lines.push(Line::Section(section));
+                    }
+                    item.depth -= section;
+                    lists[status.index()].push(item);
+                    lines.push(Line::Item(status));
+                    last = Some(status);
+                }
+                Some(Err(reason)) => {
+                    errors.push(ParseError {
+                        line: lines.len(),
+                        number: index + 1,
+                        reason,
+                    });
+                    lines.push(Line::Raw(line.to_string()));
+                    last = None;
+                }
+                None => {
+                    let last_item = last.and_then(|status| lists[status.index()].last_mut());
+                    let is_note = last_item.is_some_and(|item| self.parse_note(line, item));
+                    if !is_note {
+                        // Anything else stays where it is, and ends the notes of the item above.
+                        lines.push(Line::Raw(line.to_string()));
+                        if let Some(depth) = self.section_depth(line) {
+                            section = depth;
+                            lines.push(Line::Section(section));
+                        }
+                        last = None;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Items that don't fit go right after the last line of their list,
+    // or to the end of the file if it had none. Files in the default format get the current header.
+    // The formats that leave ids out still write the ids other items wait for.
+    fn format(&self, lists: &[Vec<Item>], lines: &[Line]) -> String {
+        let mut text = String::new();
+        if *self == Format::Todo {
+            text.push_str(&version::format_header());
+            text.push('\n');
+        }
+        let mut items: Vec<_> = lists.iter().map(|list| list.iter()).collect();
+        let mut section = 0;
+        let referenced: HashSet<u64> = lists
+            .iter()
+            .flatten()
+            .flat_map(Item::prerequisites)
+            .collect();
+        let push_item = |text: &mut String, status, item: &Item, section| {
+            let keep_id = *self != Format::Todo && referenced.contains(&item.id);
+            text.push_str(&format_nested(*self, status, item, section, keep_id));
+            text.push('\n');
+        };
+        let last_lines: Vec<Option<usize>> = Status::all()
+            .map(|status| lines.iter().rposition(|line| *line == Line::Item(status)))
+            .collect();
+        for (index, line) in lines.iter().enumerate() {
+            match line {
+                Line::Raw(line) => {
+                    text.push_str(line);
+                    text.push('\n');
+                }
+                Line::Section(depth) => section = *depth,
+                Line::Item(status) => {
+                    let items = &mut items[status.index()];
+                    let rest = if Some(index) == last_lines[status.index()] {
+                        items.len()
+                    } else {
+                        1
+                    };
+                    for item in items.by_ref().take(rest) {
+                        push_item(&mut text, *status, item, section);
+                    }
+                }
+            }
+        }
+        for status in Status::all() {
+            for item in items[status.index()].by_ref() {
+                push_item(&mut text, status, item, section);
+            }
+        }
+        text
+    }
+}
+
+// Formats an item nested `section` levels deeper than its depth in the list,
+// with its id among the metadata if asked to keep it.
+fn format_nested(
+    format: Format,
+    status: Status,
+    item: &Item,
+    section: usize,
+    keep_id: bool,
+) -> String {
+    if section == 0 && !keep_id {
+        return format.format_item(&status, item);
+    }
+    let mut item = if keep_id {
+        item.with_id()
+    } else {
+        item.clone()
+    };
+    item.depth += section;
+    format.format_item(&status, &item)
+}
+
+// struct Json stores the lists as one JSON document, the other content of the file is not kept.
+pub struct Json;
+
+impl Storage for Json {
+    fn name(&self) -> &'static str {
+        "JSON"
+    }
+
+    fn parse(
+        &self,
+        text: &str,
+        lists: &mut [Vec<Item>],
+        _lines: &mut Vec<Line>,
+        _errors: &mut Vec<ParseError>,
+    ) -> Result<(), String> {
+        json::load(text, lists)
+    }
+
+    fn format(&self, lists: &[Vec<Item>], _lines: &[Line]) -> String {
+        json::save(lists)
+    }
+}
+
+// struct Calendar stores the lists as the VTODOs of an iCalendar file,
+// its other components are kept in `lines`.
+pub struct Calendar;
+
+impl Storage for Calendar {
+    fn name(&self) -> &'static str {
+        "iCalendar"
+    }
+
+    fn parse(
+        &self,
+        text: &str,
+        lists: &mut [Vec<Item>],
+        lines: &mut Vec<Line>,
+        _errors: &mut Vec<ParseError>,
+    ) -> Result<(), String> {
+        ical::load(text, lists, lines)
+    }
+
+    fn format(&self, lists: &[Vec<Item>], lines: &[Line]) -> String {
+        ical::save(lists, lines)
+    }
+}
+
+// `.txt` files already written in the default format, like a `TODO.txt`, are kept in it.
+pub fn from_extension(file_path: &str, text: &str) -> Option<Box<dyn Storage>> {
+    let (_, extension) = file_path.rsplit_once('.')?;
+    let storage: Box<dyn Storage> = match extension {
+        "json" => Box::new(Json),
+        "ics" => Box::new(Calendar),
+        "md" | "markdown" => Box::new(Format::Markdown),
+        "txt" if is_default_format(text) => Box::new(Format::Todo),
+        "txt" => Box::new(Format::TodoTxt),
+        "org" => Box::new(Format::Org),
+        _ => return None,
+    };
+    Some(storage)
+}
+
+// Whether the first lines of the text are written in the default format: a header of todo-rs
+// or an item starting with the prefix of a column.
+fn is_default_format(text: &str) -> bool {
+    text.lines().take(SNIFF_LINES).any(|line| {
+        line.starts_with(board::HEADER_PREFIX) || Format::Todo.parse_item(line).is_some()
+    })
+}
+
+// Guesses the format from the start of the text. todo.txt is never guessed
+// as any line is a todo.txt task, and empty or unknown text is read as the default format.
+fn sniff(text: &str) -> Box<dyn Storage> {
+    let start = text.trim_start();
+    if start.starts_with('{') {
+        return Box::new(Json);
+    }
+    if start.starts_with("BEGIN:VCALENDAR") {
+        return Box::new(Calendar);
+    }
+    for line in text.lines().take(SNIFF_LINES) {
+        if line.starts_with("#+") {
+            return Box::new(Format::Org);
+        }
+        for format in [Format::Todo, Format::Markdown, Format::Org] {
+            if format.parse_item(line).is_some() {
+                return Box::new(format);
+            }
+        }
+    }
+    Box::new(Format::Todo)
+}
+
+// detect : Picks the storage of a file by its extension, or by its content for other files.
+pub fn detect(file_path: &str, text: &str) -> Box<dyn Storage> {
+    from_extension(file_path, text).unwrap_or_else(|| sniff(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::new_lists;
+
+    // Titles that need each escape of `item::ESCAPES`, and backslashes that don't.
+    const TITLES: [&str; 16] = [
+        "back\\slash",
+        "ends with a backslash\\",
+        "two\nlines",
+        "carriage\rreturn",
+        "tab\tin it",
+        " leading space",
+        "trailing space ",
+        "(A) not a priority",
+        "not a tag:value",
+        "literal \\n and \\s",
+        "escaped \\\\ twice",
+        "\\(B) literal",
+        "Markdown \\*stays\\*",
+        "[#A] not an org priority",
+        "not org :tags:",
+        "",
+    ];
+
+    fn storages() -> Vec<Box<dyn Storage>> {
+        vec![
+            Box::new(Format::Todo),
+            Box::new(Format::Markdown),
+            Box::new(Format::TodoTxt),
+            Box::new(Format::Org),
+            Box::new(Json),
+            Box::new(Calendar),
+        ]
+    }
+
+    #[test]
+    fn titles_round_trip_in_every_format() {
+        for storage in storages() {
+            for status in Status::all() {
+                let mut lists = new_lists();
+                for (id, title) in (1..).zip(TITLES) {
+                    lists[status.index()].push(Item::new(id, title));
+                }
+                let text = storage.format(&lists, &[]);
+                let mut loaded = new_lists();
+                let mut errors = Vec::new();
+                storage
+                    .load(&text, &mut loaded, &mut Vec::new(), &mut errors)
+                    .unwrap();
+                assert!(
+                    errors.is_empty(),
+                    "{}: ill-formed lines in\n{}",
+                    storage.name(),
+                    text
+                );
+                let titles: Vec<&str> = loaded[status.index()]
+                    .iter()
+                    .map(|item| item.title.as_str())
+                    .collect();
+                assert_eq!(
+                    titles,
+                    TITLES,
+                    "{} in {}:\n{}",
+                    storage.name(),
+                    status.name(),
+                    text
+                );
+            }
+        }
+    }
+}
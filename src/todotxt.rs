@@ -0,0 +1,260 @@
+use crate::date::Date;
+use crate::item::{escape_title, parse_meta, parse_tags, unescape_title, Item};
+use crate::status::{format_lines, parse_depth, Status};
+
+// Marks the notes of an item, an indented line without it is a subtask.
+pub const NOTE_MARKER: &str = "#";
+
+// Key that keeps the priority of completed tasks, as todo.txt only allows `(A)` on open ones.
+const PRIORITY_KEY: &str = "pri";
+
+// Key that tells the columns todo.txt doesn't know, `status:in-progress` or `status:cancelled`.
+// Open tasks are in the first column and completed ones in the first closed column without it.
+const STATUS_KEY: &str = "status";
+
+// Splits a leading `YYYY-MM-DD ` date off the text.
+fn parse_date(text: &str) -> (Option<Date>, &str) {
+    match text.split_once(' ') {
+        Some((date, rest)) => match Date::parse(date) {
+            Some(date) => (Some(date), rest),
+            None => (None, text),
+        },
+        None => (None, text),
+    }
+}
+
+// A title that would read as the completion mark or a date is written behind a backslash,
+// as is one that would lose its own leading backslash to it. An empty one is a lone
+// backslash, blank lines are not tasks.
+fn is_escaped(text: &str) -> bool {
+    match text.strip_prefix('\\') {
+        Some(rest) => is_escaped(rest),
+        None => text.is_empty() || text.starts_with("x ") || parse_date(text).0.is_some(),
+    }
+}
+
+// Unlike in the other formats, `key:value` words are metadata anywhere in the line,
+// `Email boss due:2026-10-03 about raise` is due on 2026-10-03.
+fn parse_text(text: &str) -> Item {
+    let mut item = Item::default();
+    let words: Vec<&str> = text
+        .split(' ')
+        .filter(|word| match parse_meta(word) {
+            Some((key, value)) => {
+                item.set_meta(key, value);
+                false
+            }
+            None => true,
+        })
+        .collect();
+    item.title = unescape_title(&words.join(" "));
+    item.tags = parse_tags(&item.title);
+    item
+}
+
+// Inverse of `parse_text`: every word of the title that would read as `key:value` gets its
+// colon escaped, not only the last one.
+fn format_text(item: &Item) -> String {
+    let title = escape_title(&item.title);
+    let mut words: Vec<String> = title
+        .split(' ')
+        .map(|word| match (parse_meta(word), word.find(':')) {
+            (Some(_), Some(colon)) => format!("{}\\{}", &word[..colon], &word[colon..]),
+            _ => word.to_string(),
+        })
+        .collect();
+    for (key, value) in item.meta.iter() {
+        words.push(format!("{}:{}", key, value));
+    }
+    words.join(" ")
+}
+
+// Reads a todo.txt task:
+//      `(A) 2026-10-18 title +project @context key:value` for open tasks,
+//      `x 2026-10-20 2026-10-18 title pri:A` for completed ones.
+// Every line that is not blank is a task, except for lines starting with NOTE_MARKER.
+// Subtasks are indented like in the other formats.
+pub fn parse_item(line: &str) -> Option<Result<(Status, Item), String>> {
+    let text = line.trim_start_matches(' ');
+    if text.is_empty() || text.starts_with(NOTE_MARKER) {
+        return None;
+    }
+    let depth = match parse_depth(line.len() - text.len()) {
+        Ok(depth) => depth,
+        Err(reason) => return Some(Err(reason)),
+    };
+
+    let (status, text) = match text.strip_prefix("x ") {
+        Some(text) => (Status::first_closed(), text),
+        None => (Status::first(), text),
+    };
+    let mut priority = None;
+    let mut text = text;
+    if let (false, [b'(', letter @ b'A'..=b'Z', b')', b' ', ..]) =
+        (status.is_closed(), text.as_bytes())
+    {
+        priority = Some(*letter as char);
+        text = &text[4..];
+    }
+    // The creation date comes first on open tasks, after the completion date on completed ones.
+    let (first, text) = parse_date(text);
+    let (completed, created, text) = if status.is_closed() {
+        let (second, text) = parse_date(text);
+        (first, second, text)
+    } else {
+        (None, first, text)
+    };
+    let text = text
+        .strip_prefix('\\')
+        .filter(|text| is_escaped(text))
+        .unwrap_or(text);
+
+    let mut item = parse_text(text);
+    item.depth = depth;
+    item.priority = priority;
+    item.created = created.map(|date| date.timestamp()).or(item.created);
+    item.completed = completed.map(|date| date.timestamp()).or(item.completed);
+    if let Some(letter) = item
+        .meta
+        .get(PRIORITY_KEY)
+        .and_then(|value| value.chars().next())
+    {
+        if status.is_closed() && letter.is_ascii_uppercase() {
+            item.priority = Some(letter);
+            item.meta.remove(PRIORITY_KEY);
+        }
+    }
+    // Completed tasks can only be in another closed column, open ones in another open column.
+    let named = item.meta.get(STATUS_KEY).and_then(|name| {
+        Status::all().find(|other| {
+            other.prefix().eq_ignore_ascii_case(name) && other.is_closed() == status.is_closed()
+        })
+    });
+    match named {
+        Some(named) => {
+            item.meta.remove(STATUS_KEY);
+            Some(Ok((named, item)))
+        }
+        None => Some(Ok((status, item))),
+    }
+}
+
+// Like the Markdown format, the id is left out unless other items wait for the item,
+// and the timestamps are reduced to days.
+pub fn format_item(status: &Status, item: &Item) -> String {
+    let created = item.created.map(Date::from_timestamp);
+    let completed = item.completed.map(Date::from_timestamp);
+    let mut line = String::new();
+    if status.is_closed() {
+        line.push_str("x ");
+        // The creation date is only allowed after a completion date.
+        if let Some(completed) = completed {
+            line.push_str(&format!("{} ", completed));
+            if let Some(created) = created {
+                line.push_str(&format!("{} ", created));
+            }
+        }
+    } else {
+        if let Some(priority) = item.priority {
+            line.push_str(&format!("({}) ", priority));
+        }
+        if let Some(created) = created {
+            line.push_str(&format!("{} ", created));
+        }
+    }
+    let mut rest = Item {
+        priority: None,
+        ..item.clone()
+    };
+    if let (true, Some(priority)) = (status.is_closed(), item.priority) {
+        rest.meta
+            .insert(PRIORITY_KEY.to_string(), priority.to_string());
+    }
+    if *status != Status::first() && *status != Status::first_closed() {
+        rest.meta
+            .insert(STATUS_KEY.to_string(), status.prefix().to_lowercase());
+    }
+    let text = format_text(&rest);
+    if is_escaped(&text) {
+        line.push('\\');
+    }
+    line.push_str(&text);
+    format_lines(item, &line, NOTE_MARKER)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::item::DUE_KEY;
+
+    fn parse(line: &str) -> (Status, Item) {
+        parse_item(line).expect("not a task").expect("ill-formed")
+    }
+
+    #[test]
+    fn reads_metadata_anywhere_in_the_line() {
+        let (status, item) = parse("(A) 2026-10-18 Email boss due:2026-10-03 about raise +work");
+        assert_eq!(status, Status::first());
+        assert_eq!(item.title, "Email boss about raise +work");
+        assert_eq!(item.priority, Some('A'));
+        assert_eq!(
+            item.created,
+            Some(Date::parse("2026-10-18").unwrap().timestamp())
+        );
+        assert_eq!(
+            item.meta.get(DUE_KEY).map(String::as_str),
+            Some("2026-10-03")
+        );
+        assert_eq!(item.tags, ["+work"]);
+    }
+
+    #[test]
+    fn keeps_ids_of_other_tools_as_metadata() {
+        let (_, item) = parse("Review PR id:abc created:yesterday");
+        assert_eq!(item.title, "Review PR");
+        assert_eq!(item.id, 0);
+        assert_eq!(item.created, None);
+        assert_eq!(item.meta.get("id").map(String::as_str), Some("abc"));
+        assert_eq!(
+            item.meta.get("created").map(String::as_str),
+            Some("yesterday")
+        );
+    }
+
+    #[test]
+    fn completed_tasks() {
+        let (status, item) = parse("x 2026-10-20 2026-10-18 Ship it pri:B status:cancelled");
+        assert!(status.is_closed());
+        assert_eq!(status.prefix(), "CANCELLED");
+        assert_eq!(item.priority, Some('B'));
+        assert_eq!(
+            item.completed,
+            Some(Date::parse("2026-10-20").unwrap().timestamp())
+        );
+        assert!(item.meta.is_empty());
+    }
+
+    #[test]
+    fn escapes_key_value_words_of_titles() {
+        let item = Item::new(1, "meet at time:10am in room:4 http://example.com");
+        let line = format_item(
+            &Status::first(),
+            &Item {
+                created: None,
+                ..item
+            },
+        );
+        assert_eq!(line, "meet at time\\:10am in room\\:4 http://example.com");
+        assert_eq!(
+            parse(&line).1.title,
+            "meet at time:10am in room:4 http://example.com"
+        );
+    }
+
+    #[test]
+    fn not_tasks() {
+        assert!(parse_item("").is_none());
+        assert!(parse_item("# a note").is_none());
+        assert!(parse_item("   x").unwrap().is_err());
+    }
+}
@@ -1,10 +1,21 @@
 use std::ops::{Add, Mul};
 use std::cmp;
+use std::io::{self, Write};
 use ncurses::*;
 
-// These 2 Variables represent color pairs.
+// These Variables represent color pairs.
 pub const REGULAR_PAIR: i16 = 0;
 pub const HIGHLIGHT_PAIR: i16 = 10;
+pub const OVERDUE_PAIR: i16 = 11;
+pub const DUE_TODAY_PAIR: i16 = 12;
+// The pair of each column comes after this one, see `Status::color`.
+pub const COLUMN_PAIR: i16 = 20;
+
+// Terminals in bracketed paste mode wrap pasted text in these, see `read_paste`.
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+// Milliseconds to wait for the rest of a paste, large pastes and pastes over SSH come in chunks.
+const PASTE_TIMEOUT: i32 = 1000;
 
 // struct Vec2 represents a 2D vector(of i32), 
 // for storing coordinates of terminal.
@@ -93,6 +104,67 @@ impl Layout {
 pub struct Ui {
     pub layouts: Vec<Layout>,
     pub key: Option<i32>,
+    pub paste: Option<String>,
+}
+
+// set_paste_mode : Asks the terminal to mark pasted text, so that a paste with newlines
+// goes into the edited title instead of being read as keys.
+pub fn set_paste_mode(enabled: bool) {
+    print!("{}", if enabled { "\x1b[?2004h" } else { "\x1b[?2004l" });
+    let _ = io::stdout().flush();
+}
+
+// read_paste : After an escape key, reads the rest of a paste if one started. Anything else
+// is given back to getch().
+pub fn read_paste(key: i32) -> Option<String> {
+    if key != 27 {
+        return None;
+    }
+    let mut keys = Vec::new();
+    for expected in PASTE_START.bytes().skip(1) {
+        let key = getch();
+        if key == ERR {
+            break;
+        }
+        keys.push(key);
+        if key != expected as i32 {
+            break;
+        }
+    }
+    if !keys.iter().copied().eq(PASTE_START.bytes().skip(1).map(i32::from)) {
+        for key in keys.into_iter().rev() {
+            ungetch(key);
+        }
+        return None;
+    }
+    // The paste is read up to its end, so that none of it runs as keys.
+    timeout(PASTE_TIMEOUT);
+    let mut bytes = Vec::new();
+    loop {
+        let key = getch();
+        if key == ERR {
+            break;
+        }
+        bytes.push(key as u8);
+        if bytes.ends_with(PASTE_END.as_bytes()) {
+            bytes.truncate(bytes.len() - PASTE_END.len());
+            break;
+        }
+    }
+    timeout(16); // back to the 60 FPS of the main loop
+    // Terminals send the newlines of a paste as carriage returns.
+    Some(String::from_utf8_lossy(&bytes).replace("\r\n", "\n").replace('\r', "\n"))
+}
+
+// Text as it is drawn, newlines of titles show as `↵` and other control characters as spaces.
+fn printable(text: &str, newline: char) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\n' => newline,
+            c if c.is_control() => ' ',
+            c => c,
+        })
+        .collect()
 }
 
 // Defines the functions to work with the UI of the App
@@ -144,7 +216,7 @@ impl Ui {
 
         mv(pos.y, pos.x);
         attron(COLOR_PAIR(pair));
-        addstr(text);
+        addstr(&printable(text, '↵'));
         attroff(COLOR_PAIR(pair));
 
         layout.add_widget(Vec2::new(width, 1));
@@ -158,9 +230,19 @@ impl Ui {
             .expect("Trying to render edit field outside of any layout");
         let pos = layout.available_pos();
 
-        if *cursor > buffer.len() {
-            *cursor = buffer.len();
+        // The cursor is a byte offset that always sits on a character boundary.
+        *cursor = (*cursor).min(buffer.len());
+        while !buffer.is_char_boundary(*cursor) {
+            *cursor -= 1;
+        }
+
+        // A paste goes in as a whole, newlines included.
+        if let Some(text) = self.paste.take() {
+            buffer.insert_str(*cursor, &text);
+            *cursor += text.len();
         }
+        let prev = buffer[..*cursor].chars().next_back().map_or(0, char::len_utf8);
+        let next = buffer[*cursor..].chars().next().map_or(0, char::len_utf8);
 
         if let Some(key) = self.key.take() {
             match key {
@@ -173,21 +255,15 @@ impl Ui {
                     *cursor += 1;
                 }
                 constants::KEY_LEFT => {
-                    if *cursor > 0 {
-                        *cursor -= 1
-                    }
+                    *cursor -= prev;
                 }
                 constants::KEY_RIGHT => {
-                    if *cursor < buffer.len() {
-                        *cursor += 1;
-                    }
+                    *cursor += next;
                 }
                 constants::KEY_BACKSPACE => {
                     if *cursor > 0 {
-                        *cursor -= 1;
-                        if *cursor < buffer.len() {
-                            buffer.remove(*cursor);
-                        }
+                        *cursor -= prev;
+                        buffer.remove(*cursor);
                     }
                 }
                 constants::KEY_DC => {
@@ -205,22 +281,23 @@ impl Ui {
         {
             mv(pos.y, pos.x);
             attron(COLOR_PAIR(REGULAR_PAIR));
-            addstr(buffer);
+            addstr(&printable(buffer, ' '));
             attroff(COLOR_PAIR(REGULAR_PAIR));
             layout.add_widget(Vec2::new(width, 1));
         }
 
         // Cursor
         {
-            mv(pos.y, pos.x + *cursor as i32);
+            let column = buffer[..*cursor].chars().count();
+            let under = buffer[*cursor..].chars().next().map_or(" ".to_string(), String::from);
+            mv(pos.y, pos.x + column as i32);
             attron(COLOR_PAIR(HIGHLIGHT_PAIR));
-            addstr(buffer.get(*cursor..=*cursor).unwrap_or(" "));
+            addstr(&printable(&under, ' '));
             attroff(COLOR_PAIR(HIGHLIGHT_PAIR));
         }
     }
 
     // label : For Rendering labels
-    #[allow(dead_code)]
     pub fn label(&mut self, text: &str, pair: i16) {
         self.label_fixed_width(text, text.len() as i32, pair);
     }
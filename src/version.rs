@@ -0,0 +1,205 @@
+use crate::date::Date;
+use crate::item::{parse_meta, Item, COMPLETED_KEY, CREATED_KEY, DUE_KEY, ID_KEY, REC_KEY};
+use crate::recur::Recurrence;
+use crate::status::Status;
+
+// Versions of the `TODO:`/`DONE:` format. Files start with a header line naming the version
+// they are written in, files without one are version 1. Lines of older files are migrated
+// to the current syntax while loading, and saving writes them back in it with the current header.
+//      1 : No header, `TODO: title` lines, later followed by `key:value` tokens.
+//      2 : The header line.
+//      3 : Backslash escapes in titles, see `item::ESCAPES`.
+pub const CURRENT_VERSION: u32 = 3;
+
+// Version of the files that have no header.
+const FIRST_VERSION: u32 = 1;
+
+const HEADER_PREFIX: &str = "# todo-rs format ";
+
+// parse_header : The version named by the first line of a file. Files without a header
+// are version 1, headers of versions newer than this build are rejected.
+pub fn parse_header(line: Option<&str>) -> Result<Option<u32>, String> {
+    let Some(version) = line.and_then(|line| line.strip_prefix(HEADER_PREFIX)) else {
+        return Ok(None);
+    };
+    match version.trim().parse() {
+        Ok(version) if version > CURRENT_VERSION => Err(format!(
+            "the file is in format {}, this version of todo-rs only reads up to format {}",
+            version, CURRENT_VERSION
+        )),
+        Ok(version) if version >= FIRST_VERSION => Ok(Some(version)),
+        _ => Err(format!("invalid format version `{}`", version.trim())),
+    }
+}
+
+pub fn format_header() -> String {
+    format!("{}{}", HEADER_PREFIX, CURRENT_VERSION)
+}
+
+// migrate_line : Rewrites a line of a file in the given version into the current syntax.
+pub fn migrate_line(version: Option<u32>, line: &str) -> String {
+    match version.unwrap_or(FIRST_VERSION) {
+        // Version 2 only added the header, titles were taken literally in both.
+        1 => escape_title(line, true),
+        2 => escape_title(line, false),
+        _ => line.to_string(),
+    }
+}
+
+// Whether a `key:value` token of a file without a header was written by todo-rs. Files from
+// before the tokens were plain titles, `meeting at time:10am` keeps its last word.
+fn is_written(key: &str, value: &str) -> bool {
+    match key {
+        ID_KEY | CREATED_KEY | COMPLETED_KEY => value.parse::<u64>().is_ok(),
+        DUE_KEY => Date::parse(value).is_some(),
+        REC_KEY => Recurrence::parse(value).is_some(),
+        _ => false,
+    }
+}
+
+// Reads the text of an item line of a file without a header, see `is_written`.
+fn parse_first_version(text: &str) -> Item {
+    let mut title = text;
+    while let Some((rest, token)) = title.rsplit_once(' ') {
+        match parse_meta(token) {
+            Some((key, value)) if is_written(key, value) => title = rest,
+            _ => break,
+        }
+    }
+    let mut item = Item::parse_raw(&text[title.len()..]);
+    match title.as_bytes() {
+        [b'(', letter @ b'A'..=b'Z', b')', b' ', ..] => {
+            item.priority = Some(*letter as char);
+            item.title = title[4..].to_string();
+        }
+        _ => item.title = title.to_string(),
+    }
+    item
+}
+
+// Backslashes in the titles of older files were taken literally, their item lines are
+// written again with the title escaped. Other lines are left as they are.
+fn escape_title(line: &str, first_version: bool) -> String {
+    let text = line.trim_start_matches(' ');
+    let indent = &line[..line.len() - text.len()];
+    let item = Status::all().find_map(|status| {
+        let text = text.strip_prefix(status.prefix())?.strip_prefix(':')?;
+        let text = text.strip_prefix(' ').or(text.is_empty().then_some(text))?;
+        let item = if first_version {
+            parse_first_version(text)
+        } else {
+            Item::parse_raw(text)
+        };
+        Some((status, item))
+    });
+    match item {
+        Some((status, item)) => format!("{}{}: {}", indent, status.prefix(), item.format()),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::status::{new_lists, Format};
+    use crate::storage::Storage;
+
+    // Loads a file in the default format and writes it back in the current version.
+    fn resave(text: &str) -> String {
+        let mut lists = new_lists();
+        let mut lines = Vec::new();
+        let mut errors = Vec::new();
+        Format::Todo
+            .load(text, &mut lists, &mut lines, &mut errors)
+            .unwrap();
+        assert!(errors.is_empty(), "ill-formed lines in\n{}", text);
+        Format::Todo.format(&lists, &lines)
+    }
+
+    #[test]
+    fn headers() {
+        assert_eq!(parse_header(None), Ok(None));
+        assert_eq!(parse_header(Some("TODO: x")), Ok(None));
+        assert_eq!(parse_header(Some("# todo-rs format 2")), Ok(Some(2)));
+        assert_eq!(
+            parse_header(Some(&format_header())),
+            Ok(Some(CURRENT_VERSION))
+        );
+        assert!(parse_header(Some("# todo-rs format 4")).is_err());
+        assert!(parse_header(Some("# todo-rs format 0")).is_err());
+        assert!(parse_header(Some("# todo-rs format x")).is_err());
+    }
+
+    #[test]
+    fn first_version_lines() {
+        let cases = [
+            ("TODO: C:\\new\\table", "TODO: C\\:\\\\new\\\\table"),
+            ("TODO: ends with \\", "TODO: ends with \\\\"),
+            ("TODO: meeting at time:10am", "TODO: meeting at time\\:10am"),
+            ("TODO: call id:bob", "TODO: call id\\:bob"),
+            ("TODO: call id:bob id:4", "TODO: call id\\:bob id:4"),
+            (
+                "TODO: ship due:2026-10-20 rec:weekly id:2",
+                "TODO: ship due:2026-10-20 rec:weekly id:2",
+            ),
+            ("TODO: not due:someday", "TODO: not due\\:someday"),
+            ("TODO: (A) urgent id:1", "TODO: (A) urgent id:1"),
+            ("DONE: (B)no space", "DONE: \\(B)no space"),
+            ("DONE:", "DONE: "),
+            ("DONE: ", "DONE: "),
+            (
+                "  TODO: nested id:3 created:100",
+                "  TODO: nested id:3 created:100",
+            ),
+            ("Some other line", "Some other line"),
+            ("", ""),
+        ];
+        for (line, migrated) in cases {
+            assert_eq!(migrate_line(None, line), migrated, "{}", line);
+            assert_eq!(migrate_line(Some(1), line), migrated, "{}", line);
+        }
+    }
+
+    #[test]
+    fn second_version_lines() {
+        let cases = [
+            ("TODO: C:\\new\\table", "TODO: C\\:\\\\new\\\\table"),
+            ("TODO: meeting id:5", "TODO: meeting id:5"),
+            (
+                "TODO: (A) urgent due:2026-10-20 id:1",
+                "TODO: (A) urgent due:2026-10-20 id:1",
+            ),
+            ("DONE:", "DONE: "),
+            ("# a comment", "# a comment"),
+        ];
+        for (line, migrated) in cases {
+            assert_eq!(migrate_line(Some(2), line), migrated, "{}", line);
+        }
+        assert_eq!(
+            migrate_line(Some(CURRENT_VERSION), "TODO: a\\nb"),
+            "TODO: a\\nb"
+        );
+    }
+
+    #[test]
+    fn headerless_files_are_saved_once() {
+        let text = "TODO: C:\\new\\table\n\
+                    TODO: meeting at time:10am\n\
+                    TODO: (A) call id:bob\n\
+                    \n\
+                    DONE:\n\
+                    DONE: shipped id:7 completed:1790899200\n";
+        let saved = resave(text);
+        assert_eq!(
+            saved,
+            "# todo-rs format 3\n\
+             TODO: C\\:\\\\new\\\\table id:8\n\
+             TODO: meeting at time\\:10am id:9\n\
+             TODO: (A) call id\\:bob id:10\n\
+             \n\
+             DONE:  id:11\n\
+             DONE: shipped id:7 completed:1790899200\n"
+        );
+        assert_eq!(resave(&saved), saved);
+    }
+}